@@ -0,0 +1,120 @@
+use std::fmt;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Characters per line of base64 data, i.e. 96 bytes. REAPER encodes each block of a plugin state
+/// on its own and does not wrap a block shorter than this: the 92-character chunk line of the
+/// plugin in StreamingPlugin.rpp is written as one line.
+pub(crate) const LINE_WIDTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeBase64Error {
+  /// Position of the offending character, counted over all lines without their line endings.
+  pub position: usize,
+}
+
+impl fmt::Display for DecodeBase64Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid base64 data at character {}", self.position)
+  }
+}
+
+impl std::error::Error for DecodeBase64Error {}
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+  let mut rv = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let v = chunk.iter().enumerate().fold(0u32, |v, (i, b)| v | (*b as u32) << (16 - 8 * i));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        rv.push(ALPHABET[(v >> (18 - 6 * i) & 0x3F) as usize] as char);
+      } else {
+        rv.push('=');
+      }
+    }
+  }
+  rv
+}
+
+/// Decodes `text`, which may be several separately padded blocks written one after the other, as
+/// REAPER does for the header, state and footer of a plugin.
+pub(crate) fn decode(text: &str) -> Result<Vec<u8>, DecodeBase64Error> {
+  let mut rv = Vec::with_capacity(text.len() / 4 * 3);
+  // Characters seen of the current group of four, and how many of them were `=`.
+  let (mut acc, mut bits, mut group, mut padding) = (0u32, 0, 0, 0);
+  for (position, c) in text.bytes().enumerate() {
+    let error = DecodeBase64Error { position };
+    if c == b'=' {
+      if group < 2 {
+        return Err(error);
+      }
+      padding += 1;
+    } else {
+      if padding > 0 {
+        return Err(error);
+      }
+      let v = ALPHABET.iter().position(|a| *a == c).ok_or(error)? as u32;
+      acc = acc << 6 | v;
+      bits += 6;
+      if bits >= 8 {
+        bits -= 8;
+        rv.push((acc >> bits) as u8);
+        acc &= (1 << bits) - 1;
+      }
+    }
+    group += 1;
+    if group == 4 {
+      (acc, bits, group, padding) = (0, 0, 0, 0);
+    }
+  }
+  if group != 0 {
+    return Err(DecodeBase64Error { position: text.len() });
+  }
+  Ok(rv)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::{parse_chunk, RFragment};
+
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    for (bytes, text) in [(&b""[..], ""), (b"f", "Zg=="), (b"fo", "Zm8="), (b"foo", "Zm9v"), (b"foob", "Zm9vYg==")] {
+      assert_eq!(encode(bytes), text);
+      assert_eq!(decode(text).unwrap(), bytes);
+    }
+    let all: Vec<u8> = (0..=255).collect();
+    assert_eq!(decode(&encode(&all)).unwrap(), all);
+  }
+
+  #[test]
+  fn padded_blocks() {
+    assert_eq!(decode("Zg==Zm8=Zm9v").unwrap(), b"ffofoo");
+    assert_eq!(decode("Zm9").unwrap_err().position, 3);
+    assert_eq!(decode("Zg=x").unwrap_err().position, 3);
+    assert_eq!(decode("Z===").unwrap_err().position, 1);
+    assert_eq!(decode("Zm 9").unwrap_err().position, 2);
+  }
+
+  #[test]
+  fn reencodes_a_state_saved_by_reaper() {
+    let project = parse_chunk(include_str!("../StreamingPlugin.rpp")).unwrap();
+    let vst = project.select(&"//VST".parse().unwrap()).next().unwrap();
+    let lines: Vec<_> = vst
+      .content
+      .iter()
+      .filter_map(|frag| match frag {
+        RFragment::BinData(line) => Some(line.as_ref()),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<_>>(), [80, 92, 20]);
+
+    let blocks: Vec<_> = lines.iter().map(|line| decode(line).unwrap()).collect();
+    let mut rewritten = vst.clone();
+    rewritten.set_bin_data_blocks(&blocks.iter().map(Vec::as_slice).collect::<Vec<_>>());
+    assert_eq!(rewritten.to_string(), vst.to_string());
+  }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use nom::error::{ErrorKind, FromExternalError, ParseError};
+
+/// A parse failure, with the position it happened at and the elements enclosing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+  /// 1-based line number.
+  pub line: usize,
+  /// 1-based column, in characters.
+  pub column: usize,
+  /// Enclosing elements, outermost first, e.g. `REAPER_PROJECT > TRACK[3] > FXCHAIN`. The index is
+  /// the element's position among siblings with the same tag, starting at 0; it is omitted for
+  /// the outermost element.
+  pub path: String,
+  pub reason: String,
+}
+
+impl Error {
+  pub(crate) fn at(input: &str, offset: usize, reason: impl Into<String>) -> Self {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Self {
+      line: before.matches('\n').count() + 1,
+      column: before[line_start..].chars().count() + 1,
+      path: element_path(input, offset),
+      reason: reason.into(),
+    }
+  }
+
+  pub(crate) fn from_failure(input: &str, failure: ParseFailure) -> Self {
+    Self::at(input, input.len() - failure.remaining, failure.reason)
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.column, self.reason)?;
+    if !self.path.is_empty() {
+      write!(f, " (in {})", self.path)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for Error {}
+
+/// Names the elements that are open at `offset` by scanning the `<TAG` and `>` lines before it.
+fn element_path(input: &str, offset: usize) -> String {
+  let mut stack: Vec<(&str, Option<usize>, HashMap<&str, usize>)> = vec![];
+  let mut line_start = 0;
+  for line in input.split_inclusive('\n') {
+    let trimmed = line.trim_start();
+    let token_start = line_start + (line.len() - trimmed.len());
+    line_start += line.len();
+    if token_start >= offset {
+      break;
+    }
+
+    if let Some(rest) = trimmed.strip_prefix('<') {
+      let tag = rest.split(|c: char| c.is_whitespace()).next().unwrap_or_default();
+      let index = stack.last_mut().map(|(_, _, siblings)| {
+        let count = siblings.entry(tag).or_default();
+        *count += 1;
+        *count - 1
+      });
+      stack.push((tag, index, HashMap::new()));
+    } else if trimmed.starts_with('>') {
+      stack.pop();
+    }
+  }
+
+  stack
+    .iter()
+    .map(|(tag, index, _)| match index {
+      Some(index) => format!("{tag}[{index}]"),
+      None => tag.to_string(),
+    })
+    .collect::<Vec<_>>()
+    .join(" > ")
+}
+
+/// nom error type used by the `Result`-returning entry points. Only the position is kept, as the
+/// number of bytes left unparsed.
+#[derive(Debug)]
+pub(crate) struct ParseFailure {
+  pub remaining: usize,
+  pub reason: String,
+}
+
+impl<'a> ParseError<&'a str> for ParseFailure {
+  fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+    Self {
+      remaining: input.len(),
+      reason: format!("{} failed", kind.description()),
+    }
+  }
+
+  fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+    other
+  }
+
+  fn from_char(input: &'a str, c: char) -> Self {
+    let reason = match c {
+      c if input.is_empty() => format!("unexpected end of input, expected `{c}`"),
+      '>' => "expected an attribute, base64 data, a child element or `>`".to_string(),
+      c => format!("expected `{c}`"),
+    };
+    Self {
+      remaining: input.len(),
+      reason,
+    }
+  }
+}
+
+impl<'a, X> FromExternalError<&'a str, X> for ParseFailure {
+  fn from_external_error(input: &'a str, kind: ErrorKind, _: X) -> Self {
+    Self::from_error_kind(input, kind)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn path_counts_siblings_per_tag() {
+    let input = "<REAPER_PROJECT\n  <TRACK\n  >\n  <TRACK\n    <FXCHAIN\n      BAD\n";
+    let offset = input.find("BAD").unwrap();
+    assert_eq!(element_path(input, offset), "REAPER_PROJECT > TRACK[1] > FXCHAIN[0]");
+  }
+
+  #[test]
+  fn position_is_one_based() {
+    let input = "<A\n  B 1\n  ?\n>";
+    let error = Error::at(input, input.find('?').unwrap(), "bad");
+    assert_eq!((error.line, error.column), (3, 3));
+    assert_eq!(error.to_string(), "3:3: bad (in A)");
+  }
+}
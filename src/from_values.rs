@@ -0,0 +1,124 @@
+use crate::{Guid, RValue};
+
+/// Conversion from a single [`RValue`].
+pub trait FromRValue<'v>: Sized {
+  fn from_rvalue(value: &'v RValue) -> Option<Self>;
+}
+
+/// Conversion from the positional values of an attribute, such as `VOLPAN 1 0 -1 -1 1`.
+///
+/// Implemented for tuples of [`FromRValue`] types. Values past the end of the tuple are ignored;
+/// `Option` fields are `None` when the value is missing, which covers attributes that older REAPER
+/// versions write with fewer values.
+pub trait FromRValues<'v>: Sized {
+  fn from_rvalues(values: &'v [RValue]) -> Option<Self>;
+}
+
+impl<'v> FromRValue<'v> for f64 {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_num()
+  }
+}
+
+impl<'v> FromRValue<'v> for f32 {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_num().map(|f| f as f32)
+  }
+}
+
+impl<'v> FromRValue<'v> for i64 {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_i64()
+  }
+}
+
+impl<'v> FromRValue<'v> for i32 {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_i64().and_then(|i| i.try_into().ok())
+  }
+}
+
+impl<'v> FromRValue<'v> for u32 {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_i64().and_then(|i| i.try_into().ok())
+  }
+}
+
+impl<'v> FromRValue<'v> for usize {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_i64().and_then(|i| i.try_into().ok())
+  }
+}
+
+impl<'v> FromRValue<'v> for bool {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_bool()
+  }
+}
+
+impl<'v> FromRValue<'v> for &'v str {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_str()
+  }
+}
+
+impl<'v> FromRValue<'v> for String {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_str().map(str::to_string)
+  }
+}
+
+impl<'v> FromRValue<'v> for Guid {
+  fn from_rvalue(value: &'v RValue) -> Option<Self> {
+    value.get_guid()
+  }
+}
+
+/// Tuple field lookup: `T` requires the value to be present, `Option<T>` does not.
+pub trait FromRValueAt<'v>: Sized {
+  fn from_rvalue_at(values: &'v [RValue], index: usize) -> Option<Self>;
+}
+
+impl<'v, T: FromRValue<'v>> FromRValueAt<'v> for T {
+  fn from_rvalue_at(values: &'v [RValue], index: usize) -> Option<Self> {
+    values.get(index).and_then(T::from_rvalue)
+  }
+}
+
+impl<'v, T: FromRValue<'v>> FromRValueAt<'v> for Option<T> {
+  fn from_rvalue_at(values: &'v [RValue], index: usize) -> Option<Self> {
+    match values.get(index) {
+      Some(value) => T::from_rvalue(value).map(Some),
+      None => Some(None),
+    }
+  }
+}
+
+impl<'v, T: FromRValue<'v>> FromRValues<'v> for Vec<T> {
+  fn from_rvalues(values: &'v [RValue]) -> Option<Self> {
+    values.iter().map(T::from_rvalue).collect()
+  }
+}
+
+macro_rules! tuple_from_rvalues {
+  ($($name:ident $index:tt),+) => {
+    impl<'v, $($name: FromRValueAt<'v>),+> FromRValues<'v> for ($($name,)+) {
+      fn from_rvalues(values: &'v [RValue]) -> Option<Self> {
+        Some(($($name::from_rvalue_at(values, $index)?,)+))
+      }
+    }
+  };
+}
+
+tuple_from_rvalues!(A 0);
+tuple_from_rvalues!(A 0, B 1);
+tuple_from_rvalues!(A 0, B 1, C 2);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_from_rvalues!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
@@ -0,0 +1,106 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}` identifier, as used by `TRACKID`, `FXID`, `IGUID` etc.
+///
+/// Stored as the 128-bit number the hex digits spell out, in the order they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Guid(pub u128);
+
+impl fmt::Display for Guid {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let v = self.0;
+    write!(
+      f,
+      "{{{:08X}-{:04X}-{:04X}-{:04X}-{:012X}}}",
+      (v >> 96) as u32,
+      (v >> 80) as u16,
+      (v >> 64) as u16,
+      (v >> 48) as u16,
+      v & 0xFFFF_FFFF_FFFF,
+    )
+  }
+}
+
+impl Guid {
+  /// A new random (version 4) GUID, for `FXID`s and `TRACKID`s of elements created in code.
+  ///
+  /// The randomness comes from the per-process random keys of the standard library's `HashMap`,
+  /// mixed with the time and a counter, which is plenty for IDs that only need to be unique.
+  pub fn generate() -> Self {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let half = |salt: u8| RandomState::new().hash_one((salt, nanos, count)) as u128;
+    let v = half(0) << 64 | half(1);
+    Guid(v & !(0xF << 76 | 0x3 << 62) | 0x4 << 76 | 0x2 << 62)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGuidError;
+
+impl fmt::Display for ParseGuidError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("expected a GUID like {E8B281C6-3542-394A-AAD4-A5875512F906}")
+  }
+}
+
+impl std::error::Error for ParseGuidError {}
+
+impl FromStr for Guid {
+  type Err = ParseGuidError;
+
+  /// Accepts upper case hex digits only, so that parsed GUIDs are written back unchanged.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let inner = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).ok_or(ParseGuidError)?;
+    let groups: Vec<&str> = inner.split('-').collect();
+    let lengths = groups.iter().map(|g| g.len()).collect::<Vec<_>>();
+    if lengths != [8, 4, 4, 4, 12] {
+      return Err(ParseGuidError);
+    }
+
+    let mut v = 0u128;
+    for c in groups.concat().chars() {
+      let digit = match c {
+        '0'..='9' | 'A'..='F' => c.to_digit(16).ok_or(ParseGuidError)?,
+        _ => return Err(ParseGuidError),
+      };
+      v = (v << 4) | digit as u128;
+    }
+    Ok(Guid(v))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let text = "{E8B281C6-3542-394A-AAD4-A5875512F906}";
+    let guid: Guid = text.parse().unwrap();
+    assert_eq!(guid.0, 0xE8B281C63542394AAAD4A5875512F906);
+    assert_eq!(guid.to_string(), text);
+  }
+
+  #[test]
+  fn rejects_other_shapes() {
+    assert!("E8B281C6-3542-394A-AAD4-A5875512F906".parse::<Guid>().is_err());
+    assert!("{e8b281c6-3542-394a-aad4-a5875512f906}".parse::<Guid>().is_err());
+    assert!("{E8B281C6-3542-394A-AAD4A5875512F906}".parse::<Guid>().is_err());
+    assert!("{}".parse::<Guid>().is_err());
+  }
+
+  #[test]
+  fn generated_guids_differ() {
+    let (a, b) = (Guid::generate(), Guid::generate());
+    assert_ne!(a, b);
+    assert_eq!(a.to_string().parse::<Guid>(), Ok(a));
+    assert_eq!(&a.to_string()[15..16], "4");
+  }
+}
@@ -1,180 +1,573 @@
 #[cfg(test)]
 #[macro_use]
 extern crate assert_matches;
-#[macro_use]
-extern crate nom;
-
-use std::collections::HashMap;
-use std::convert::identity;
-
-use nom::combinator::value;
-
-pub use parser::parse_element;
-pub use reaper::Project;
 
-pub(self) mod parser;
-pub(self) mod reaper;
-
-#[derive(Debug, PartialEq)]
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+pub use base64::DecodeBase64Error;
+pub use error::Error;
+pub use from_values::{FromRValue, FromRValueAt, FromRValues};
+pub use guid::Guid;
+pub use parser::{parse_chunk, parse_chunk_recovering, parse_element, parse_project, parse_project_recovering, Event, Events, Recovered};
+pub use reader::ProjectReader;
+pub use reaper::{
+  amplitude_to_db, db_to_amplitude, Channels, DetectorInput, EqBand, EqBandType, Fade, FadeShape, FxChain, FxChainMut, FxEntry, Item,
+  ItemMut, JsPlugin, Plugin, PluginStateError, Project, ReaComp, ReaEq, RecordMonitor, RouteKind, RoutingEdge,
+  RoutingGraph, RoutingNode, SendMode, SendParams, Solo, StreamingConfig, Track, TrackMut, TrackNode, VstId, VstPlugin,
+  REACOMP_ID, REAEQ_ID, STREAMING_PLUGIN_ID,
+};
+pub use select::Selector;
+pub use writer::{ChunkWriter, LineEnding};
+
+mod base64;
+mod error;
+mod from_values;
+mod guid;
+mod parser;
+mod reader;
+mod reaper;
+mod select;
+mod writer;
+
+/// A single line (or nested element) inside an element body.
+///
+/// Text is held as [`Cow`] so that a parsed tree borrows from its input, while
+/// [`RFragment::into_owned`] detaches it into an `RFragment<'static>`.
+#[derive(Debug, Clone, PartialEq)]
 pub enum RFragment<'a> {
-  Attribute(&'a str, Vec<RValue<'a>>),
+  Attribute(Cow<'a, str>, Vec<RValue<'a>>),
   Child(RElement<'a>),
-  BinData(String),
+  BinData(Cow<'a, str>),
   Empty,
+  /// A line the recovering parser could not make sense of, kept verbatim (without indentation).
+  Raw(Cow<'a, str>),
+}
+
+impl<'a> RFragment<'a> {
+  pub fn into_owned(self) -> RFragment<'static> {
+    match self {
+      RFragment::Attribute(name, values) => RFragment::Attribute(
+        Cow::Owned(name.into_owned()),
+        values.into_iter().map(RValue::into_owned).collect(),
+      ),
+      RFragment::Child(child) => RFragment::Child(child.into_owned()),
+      RFragment::BinData(data) => RFragment::BinData(Cow::Owned(data.into_owned())),
+      RFragment::Empty => RFragment::Empty,
+      RFragment::Raw(raw) => RFragment::Raw(Cow::Owned(raw.into_owned())),
+    }
+  }
 }
 
-pub(crate) fn is_fragment_attribute<'a, 'b>(name: &'b str) -> impl Fn(&'a RFragment<'a>) -> Option<&'a Vec<RValue<'a>>> + 'b
-  where
-    'a: 'b,
-{
+pub(crate) fn is_fragment_attribute<'f, 'a: 'f, 'n>(name: &'n str) -> impl Fn(&'f RFragment<'a>) -> Option<&'f Vec<RValue<'a>>> + 'n {
   move |frag| match frag {
-    RFragment::Attribute(attrib_name, values) if *attrib_name == name => Some(values),
+    RFragment::Attribute(attrib_name, values) if attrib_name == name => Some(values),
     _ => None,
   }
 }
 
-pub(crate) fn is_child_tag<'a, 'b>(tag: &'b str) -> impl Fn(&'a RFragment<'a>) -> Option<&'a RElement<'a>> + 'b
-  where
-    'a: 'b,
-{
+pub(crate) fn is_child_tag<'f, 'a: 'f, 'n>(tag: &'n str) -> impl Fn(&'f RFragment<'a>) -> Option<&'f RElement<'a>> + 'n {
   move |frag| match frag {
     RFragment::Child(c) if c.tag == tag => Some(c),
     _ => None,
   }
 }
 
-#[derive(Default, Debug, PartialEq)]
+/// An RPP element (`<TAG args...` ... `>`).
+///
+/// Elements returned by [`parse_element`] borrow from the parsed input; use
+/// [`RElement::into_owned`] to get an `RElement<'static>` that can outlive it.
+///
+/// Equality ignores [`RElement::layout`].
+#[derive(Default, Debug, Clone)]
 pub struct RElement<'a> {
-  pub tag: &'a str,
+  pub tag: Cow<'a, str>,
   pub args: Vec<RValue<'a>>,
   pub content: Vec<RFragment<'a>>,
+  /// Source formatting, set by the parser. `None` for elements built in code.
+  pub layout: Option<RLayout<'a>>,
+}
+
+impl<'a, 'b> PartialEq<RElement<'b>> for RElement<'a> {
+  fn eq(&self, other: &RElement<'b>) -> bool {
+    self.tag == other.tag && self.args == other.args && self.content == other.content
+  }
+}
+
+/// Whitespace and line endings of a parsed element, so that [`RElement::to_string`] can write a
+/// file that was only read back byte-for-byte.
+///
+/// Only the whitespace around each line is recorded: values are written back from their
+/// [`RValue`]s, separated by single spaces the way REAPER writes them. Elements without a layout
+/// take their formatting from the enclosing element.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RLayout<'a> {
+  /// Whitespace before `<TAG`.
+  pub indent: Cow<'a, str>,
+  /// Whitespace after the arguments on the `<TAG` line.
+  pub header_trailing: Cow<'a, str>,
+  /// Whitespace before body lines that have no entry in `lines`, taken from the first body line.
+  pub body_indent: Option<Cow<'a, str>>,
+  /// The whitespace around each fragment of [`RElement::content`], in the same order, and `None`
+  /// for fragments added after parsing. Ignored if its length does not match the content, e.g.
+  /// after `content` was edited directly instead of through the methods of [`RElement`].
+  pub lines: Vec<Option<RLineLayout<'a>>>,
+  /// Whitespace before the closing `>`.
+  pub close_indent: Cow<'a, str>,
+  /// `\n` or `\r\n`.
+  pub line_ending: Cow<'a, str>,
+  /// What follows the closing `>` of the outermost element, if [`parse_chunk`] read it. `None`
+  /// ends the element with a line ending.
+  pub end: Option<Cow<'a, str>>,
+}
+
+/// The whitespace around one line of an element body.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RLineLayout<'a> {
+  /// Whitespace before the line, or before `<TAG` for a child element.
+  pub indent: Cow<'a, str>,
+  /// Whitespace before the line ending, or after the closing `>` for a child element.
+  pub trailing: Cow<'a, str>,
+}
+
+impl<'a> RLayout<'a> {
+  pub fn into_owned(self) -> RLayout<'static> {
+    RLayout {
+      indent: Cow::Owned(self.indent.into_owned()),
+      header_trailing: Cow::Owned(self.header_trailing.into_owned()),
+      body_indent: self.body_indent.map(|i| Cow::Owned(i.into_owned())),
+      lines: self.lines.into_iter().map(|line| line.map(RLineLayout::into_owned)).collect(),
+      close_indent: Cow::Owned(self.close_indent.into_owned()),
+      line_ending: Cow::Owned(self.line_ending.into_owned()),
+      end: self.end.map(|end| Cow::Owned(end.into_owned())),
+    }
+  }
+}
+
+impl<'a> RLineLayout<'a> {
+  pub fn into_owned(self) -> RLineLayout<'static> {
+    RLineLayout {
+      indent: Cow::Owned(self.indent.into_owned()),
+      trailing: Cow::Owned(self.trailing.into_owned()),
+    }
+  }
 }
 
 impl<'a> RElement<'a> {
-  pub fn append_attribute(&mut self, name: &'a str, values: Vec<RValue<'a>>) {
-    self.content.push(RFragment::Attribute(name, values));
+  pub fn new(tag: impl Into<Cow<'a, str>>, args: Vec<RValue<'a>>) -> Self {
+    Self {
+      tag: tag.into(),
+      args,
+      content: vec![],
+      layout: None,
+    }
+  }
+
+  pub fn into_owned(self) -> RElement<'static> {
+    RElement {
+      tag: Cow::Owned(self.tag.into_owned()),
+      args: self.args.into_iter().map(RValue::into_owned).collect(),
+      content: self.content.into_iter().map(RFragment::into_owned).collect(),
+      layout: self.layout.map(RLayout::into_owned),
+    }
+  }
+
+  pub fn append_attribute(&mut self, name: impl Into<Cow<'a, str>>, values: Vec<RValue<'a>>) {
+    self.insert_fragment_at(self.content.len(), RFragment::Attribute(name.into(), values));
   }
 
-  pub fn append_bin_data(&mut self, data: String) {
-    self.content.push(RFragment::BinData(data));
+  pub fn append_bin_data(&mut self, data: impl Into<Cow<'a, str>>) {
+    self.insert_fragment_at(self.content.len(), RFragment::BinData(data.into()));
   }
 
   pub fn append_child(&mut self, child: RElement<'a>) {
-    self.content.push(RFragment::Child(child));
+    self.insert_fragment_at(self.content.len(), RFragment::Child(child));
   }
 
-  pub fn get_str_arg(&'a self, index: usize) -> Option<&'a str> {
+  pub fn get_str_arg(&self, index: usize) -> Option<&str> {
     self.args.get(index).and_then(RValue::get_str)
   }
 
-  pub fn get_str_attr<'b>(&'a self, name: &'b str, index: usize) -> Option<&'b str>
-    where
-      'a: 'b,
-  {
-    self.content
-      .iter()
-      .filter_map(is_fragment_attribute(name))
-      .nth(index)
-      .and_then(|x| x.first())
-      .and_then(RValue::get_str)
+  /// The first value of the `index`-th attribute called `name`.
+  fn attr_value(&self, name: &str, index: usize) -> Option<&RValue<'a>> {
+    self.attr_values_nth(name, index)?.first()
+  }
+
+  pub fn get_str_attr(&self, name: &str, index: usize) -> Option<&str> {
+    self.attr_value(name, index).and_then(RValue::get_str)
+  }
+
+  pub fn get_num_attr(&self, name: &str, index: usize) -> Option<f64> {
+    self.attr_value(name, index).and_then(RValue::get_num)
   }
 
-  pub fn get_num_attr(&'a self, name: &'a str, index: usize) -> Option<f64> {
+  /// All values of the first attribute called `name`.
+  pub fn attr_values(&self, name: &str) -> Option<&[RValue<'a>]> {
+    self.attr_values_nth(name, 0)
+  }
+
+  /// All values of the `index`-th attribute called `name`.
+  pub fn attr_values_nth(&self, name: &str, index: usize) -> Option<&[RValue<'a>]> {
     self.content
       .iter()
       .filter_map(is_fragment_attribute(name))
       .nth(index)
-      .and_then(|x| x.first())
-      .and_then(RValue::get_num)
+      .map(Vec::as_slice)
+  }
+
+  /// Converts the values of the first attribute called `name`, e.g.
+  /// `let (vol, pan): (f64, f64) = track.attr("VOLPAN")?;`.
+  pub fn attr<'s, T: FromRValues<'s>>(&'s self, name: &str) -> Option<T> {
+    self.attr_nth(name, 0)
+  }
+
+  pub fn attr_nth<'s, T: FromRValues<'s>>(&'s self, name: &str, index: usize) -> Option<T> {
+    self.attr_values_nth(name, index).and_then(T::from_rvalues)
+  }
+
+  /// Converts the element's arguments, e.g. `let (name, file): (&str, &str) = vst.args_as()?;`.
+  pub fn args_as<'s, T: FromRValues<'s>>(&'s self) -> Option<T> {
+    T::from_rvalues(&self.args)
+  }
+
+  pub fn get_i64_attr(&self, name: &str, index: usize) -> Option<i64> {
+    self.attr_value(name, index).and_then(RValue::get_i64)
+  }
+
+  pub fn get_bool_attr(&self, name: &str, index: usize) -> Option<bool> {
+    self.attr_value(name, index).and_then(RValue::get_bool)
+  }
+
+  pub fn get_guid_attr(&self, name: &str, index: usize) -> Option<Guid> {
+    self.attr_value(name, index).and_then(RValue::get_guid)
   }
 
-  pub fn children_with_tag<'b>(&'a self, tag: &'b str) -> impl Iterator<Item=&'a RElement<'a>> + 'b
+  pub fn children_with_tag<'s>(&'s self, tag: &'s str) -> impl Iterator<Item=&'s RElement<'a>> + 's {
+    self.content.iter().filter_map(is_child_tag(tag))
+  }
+
+  pub fn children_with_tag_mut<'s>(&'s mut self, tag: &'s str) -> impl Iterator<Item=&'s mut RElement<'a>> + 's {
+    self.content.iter_mut().filter_map(move |frag| match frag {
+      RFragment::Child(c) if c.tag == tag => Some(c),
+      _ => None,
+    })
+  }
+
+  pub fn children_mut(&mut self) -> impl Iterator<Item=&mut RElement<'a>> {
+    self.content.iter_mut().filter_map(|frag| match frag {
+      RFragment::Child(c) => Some(c),
+      _ => None,
+    })
+  }
+
+  /// Values of the first attribute called `name`, for in-place editing.
+  pub fn attr_mut(&mut self, name: &str) -> Option<&mut Vec<RValue<'a>>> {
+    self.content.iter_mut().find_map(|frag| match frag {
+      RFragment::Attribute(attrib_name, values) if attrib_name == name => Some(values),
+      _ => None,
+    })
+  }
+
+  /// Replaces the values of the first attribute called `name`, or appends the attribute if the
+  /// element does not have it yet.
+  pub fn set_attr(&mut self, name: impl Into<Cow<'a, str>>, values: Vec<RValue<'a>>) {
+    let name = name.into();
+    match self.attr_mut(&name) {
+      Some(existing) => *existing = values,
+      None => self.append_attribute(name, values),
+    }
+  }
+
+  /// Removes every attribute called `name`, returning how many were removed.
+  pub fn remove_attr(&mut self, name: &str) -> usize {
+    let before = self.content.len();
+    self.retain(|frag| !matches!(frag, RFragment::Attribute(attrib_name, _) if attrib_name == name));
+    before - self.content.len()
+  }
+
+  /// Removes every direct child with the given tag and returns them in document order.
+  pub fn remove_children_with_tag(&mut self, tag: &str) -> Vec<RElement<'a>> {
+    let keep: Vec<_> = self.content.iter().map(|frag| !matches!(frag, RFragment::Child(c) if c.tag == tag)).collect();
+    if let Some(lines) = self.layout_lines_mut() {
+      let mut keep = keep.iter();
+      lines.retain(|_| *keep.next().unwrap());
+    }
+    let mut removed = vec![];
+    let mut kept = Vec::with_capacity(self.content.len());
+    for frag in self.content.drain(..) {
+      match frag {
+        RFragment::Child(c) if c.tag == tag => removed.push(c),
+        frag => kept.push(frag),
+      }
+    }
+    self.content = kept;
+    removed
+  }
+
+  /// Inserts `child` at `index` in the content list (not the child list), shifting later
+  /// fragments. Panics if `index > content.len()`, like [`Vec::insert`].
+  pub fn insert_child_at(&mut self, index: usize, child: RElement<'a>) {
+    self.insert_fragment_at(index, RFragment::Child(child));
+  }
+
+  pub fn insert_attribute_at(&mut self, index: usize, name: impl Into<Cow<'a, str>>, values: Vec<RValue<'a>>) {
+    self.insert_fragment_at(index, RFragment::Attribute(name.into(), values));
+  }
+
+  pub fn insert_fragment_at(&mut self, index: usize, fragment: RFragment<'a>) {
+    self.splice_content(index..index, [fragment]);
+  }
+
+  /// Replaces the fragments in `range` with `fragments` and returns the removed ones, like
+  /// [`Vec::splice`]. The other fragments keep their recorded [`RLineLayout`].
+  pub fn splice_content<I>(&mut self, range: Range<usize>, fragments: I) -> Vec<RFragment<'a>>
     where
-      'a: 'b,
+      I: IntoIterator<Item = RFragment<'a>>,
   {
-    self.content.iter().filter_map(is_child_tag(tag))
+    let synced = self.layout_lines_mut().is_some();
+    let before = self.content.len();
+    let removed: Vec<_> = self.content.splice(range.clone(), fragments).collect();
+    let added = self.content.len() + removed.len() - before;
+    if let Some(layout) = self.layout.as_mut().filter(|_| synced) {
+      layout.lines.splice(range, std::iter::repeat_n(None, added));
+    }
+    removed
+  }
+
+  pub fn retain<F>(&mut self, mut f: F)
+    where
+      F: FnMut(&RFragment<'a>) -> bool,
+  {
+    self.retain_mut(|frag| f(frag));
+  }
+
+  pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+      F: FnMut(&mut RFragment<'a>) -> bool,
+  {
+    let keep: Vec<_> = self.content.iter_mut().map(&mut f).collect();
+    if let Some(lines) = self.layout_lines_mut() {
+      let mut line_keep = keep.iter();
+      lines.retain(|_| *line_keep.next().unwrap());
+    }
+    let mut keep = keep.into_iter();
+    self.content.retain(|_| keep.next().unwrap());
+  }
+
+  /// The recorded [`RLineLayout`]s, if they still match the content.
+  fn layout_lines_mut(&mut self) -> Option<&mut Vec<Option<RLineLayout<'a>>>> {
+    let len = self.content.len();
+    self.layout.as_mut().map(|layout| &mut layout.lines).filter(|lines| lines.len() == len)
+  }
+
+  /// Decodes the first run of consecutive [`RFragment::BinData`] lines, e.g. a plugin state or a
+  /// `RENDER_CFG` blob. Elements without base64 data give an empty vector.
+  pub fn bin_data_bytes(&self) -> Result<Vec<u8>, DecodeBase64Error> {
+    let text: String = self.content[self.bin_data_range()]
+      .iter()
+      .filter_map(|frag| match frag {
+        RFragment::BinData(line) => Some(line.as_ref()),
+        _ => None,
+      })
+      .collect();
+    base64::decode(&text)
+  }
+
+  /// Replaces the first run of base64 lines with `bytes`, wrapped at the line width REAPER uses,
+  /// or appends them if there is none.
+  pub fn set_bin_data_bytes(&mut self, bytes: &[u8]) {
+    self.set_bin_data_blocks(&[bytes]);
+  }
+
+  /// Like [`RElement::set_bin_data_bytes`], but each block is encoded and wrapped on its own, the
+  /// way REAPER writes the header, state and trailer of a plugin.
+  pub fn set_bin_data_blocks(&mut self, blocks: &[&[u8]]) {
+    let lines: Vec<_> = blocks
+      .iter()
+      .flat_map(|block| {
+        let text = base64::encode(block);
+        let lines: Vec<_> = text
+          .as_bytes()
+          .chunks(base64::LINE_WIDTH)
+          .map(|line| RFragment::BinData(String::from_utf8_lossy(line).into_owned().into()))
+          .collect();
+        lines
+      })
+      .collect();
+    let range = self.bin_data_range();
+    self.splice_content(range, lines);
+  }
+
+  fn bin_data_range(&self) -> Range<usize> {
+    let is_bin_data = |frag: &RFragment| matches!(frag, RFragment::BinData(_));
+    let start = self.content.iter().position(is_bin_data).unwrap_or(self.content.len());
+    let len = self.content[start..].iter().take_while(|frag| is_bin_data(frag)).count();
+    start..start + len
+  }
+
+  /// Index into `content` of the fragment identified by `id`.
+  pub fn position(&self, id: &RFragmentId) -> Option<usize> {
+    let mut iter = self.content.iter().enumerate();
+    match id {
+      RFragmentId::Attribute(name) => iter
+        .find(|(_, frag)| matches!(frag, RFragment::Attribute(attrib_name, _) if attrib_name == name))
+        .map(|(i, _)| i),
+      RFragmentId::BinData(n) => iter
+        .filter(|(_, frag)| matches!(frag, RFragment::BinData(_)))
+        .nth(*n)
+        .map(|(i, _)| i),
+      RFragmentId::Child(n) => iter
+        .filter(|(_, frag)| matches!(frag, RFragment::Child(_)))
+        .nth(*n)
+        .map(|(i, _)| i),
+    }
+  }
+
+  pub fn fragment(&self, id: &RFragmentId) -> Option<&RFragment<'a>> {
+    self.position(id).map(|i| &self.content[i])
+  }
+
+  pub fn fragment_mut(&mut self, id: &RFragmentId) -> Option<&mut RFragment<'a>> {
+    self.position(id).map(move |i| &mut self.content[i])
+  }
+
+  pub fn replace_fragment(&mut self, id: &RFragmentId, fragment: RFragment<'a>) -> Option<RFragment<'a>> {
+    self.fragment_mut(id).map(|existing| std::mem::replace(existing, fragment))
+  }
+
+  pub fn remove_fragment(&mut self, id: &RFragmentId) -> Option<RFragment<'a>> {
+    self.position(id).map(|i| self.splice_content(i..i + 1, []).remove(0))
   }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Identifies a fragment inside an element's content: the first attribute with a given name, or
+/// the n-th bin data line / child element.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RFragmentId<'a> {
   Attribute(&'a str),
   BinData(usize),
   Child(usize),
 }
 
-impl<'a> ToString for RElement<'a> {
-  fn to_string(&self) -> String {
-    self.to_string_with_indent(0)
+/// Writes the element back in RPP syntax, keeping the recorded [`RLayout`] of parsed elements.
+/// Use [`RElement::to_string_with_indent`] or a [`ChunkWriter`] for normalized output.
+impl<'a> fmt::Display for RElement<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.layout.as_ref().and_then(|layout| layout.end.as_deref()) {
+      Some(end) => {
+        self.write_to(f, "", " ", "\n", true, None)?;
+        f.write_str(end)
+      }
+      None => self.write_to(f, "", " ", "\n", true, Some("")),
+    }
   }
 }
 
 impl<'a> RElement<'a> {
+  /// Writes the element with `indent` spaces before the opening tag, one more per nesting level
+  /// and `\n` line endings, ignoring any recorded layout.
   pub fn to_string_with_indent(&self, indent: usize) -> String {
     let mut rv = String::new();
-    let prefix = " ".repeat(indent);
-    let inner_prefix = " ".repeat(indent + 1);
-    let tag = self.tag;
-    let args = Self::value_list_to_string(&self.args);
-    let arg_space = if self.args.len() > 0 { " " } else { "" };
-
-    rv.push_str(&format!(
-      "{prefix}<{tag}{arg_space}{args}\n",
-      prefix = &prefix,
-      tag = &tag,
-      arg_space = &arg_space,
-      args = &args
-    ));
-
-    for frag in &self.content {
+    self
+      .write_to(&mut rv, &" ".repeat(indent), " ", "\n", false, Some(""))
+      .expect("writing to a String cannot fail");
+    rv
+  }
+
+  /// Writes the element starting at `indent`, nesting children by `step`. Parsed elements use
+  /// their own layout instead if `keep_layout` is set. `close_line` is the whitespace written
+  /// after the closing `>`, followed by a line ending; `None` ends the output at the `>`.
+  pub(crate) fn write_to<W: fmt::Write + ?Sized>(
+    &self,
+    rv: &mut W,
+    indent: &str,
+    step: &str,
+    line_ending: &str,
+    keep_layout: bool,
+    close_line: Option<&str>,
+  ) -> fmt::Result {
+    let layout = self.layout.as_ref().filter(|_| keep_layout);
+    let indent = layout.map_or(indent, |l| &l.indent);
+    let line_ending = layout.map_or(line_ending, |l| &l.line_ending);
+    let close_indent = layout.map_or(indent, |l| &l.close_indent);
+    let inner_prefix = match layout.and_then(|l| l.body_indent.as_deref()) {
+      Some(body_indent) => Cow::Borrowed(body_indent),
+      None => Cow::Owned(format!("{indent}{step}")),
+    };
+    let step = inner_prefix.strip_prefix(indent).filter(|s| !s.is_empty()).unwrap_or(step);
+
+    let header_trailing = layout.map_or("", |l| &l.header_trailing);
+    let lines = layout.map(|l| &l.lines[..]).filter(|lines| lines.len() == self.content.len());
+
+    write!(rv, "{indent}<{}", self.tag)?;
+    Self::write_value_list(rv, &self.args)?;
+    write!(rv, "{header_trailing}{line_ending}")?;
+
+    for (i, frag) in self.content.iter().enumerate() {
+      let line = lines.and_then(|lines| lines[i].as_ref());
+      let prefix = line.map_or(&*inner_prefix, |l| &l.indent);
+      let trailing = line.map_or("", |l| &l.trailing);
       match frag {
         RFragment::Attribute(id, value_list) => {
-          let value_list = Self::value_list_to_string(value_list);
-          rv.push_str(&format!(
-            "{inner_prefix}{id} {value_list}\n",
-            inner_prefix = &inner_prefix,
-            id = &id,
-            value_list = &value_list
-          ));
+          write!(rv, "{prefix}{id}")?;
+          Self::write_value_list(rv, value_list)?;
+          write!(rv, "{trailing}{line_ending}")?;
         }
-        RFragment::BinData(bin_data) => {
-          rv.push_str(&format!(
-            "{inner_prefix}{bin_data}\n",
-            inner_prefix = &inner_prefix,
-            bin_data = &bin_data
-          ));
+        RFragment::BinData(text) | RFragment::Raw(text) => {
+          write!(rv, "{prefix}{text}{trailing}{line_ending}")?;
         }
         RFragment::Child(child) => {
-          rv.push_str(&child.to_string_with_indent(indent + 1));
+          child.write_to(rv, prefix, step, line_ending, keep_layout, Some(trailing))?;
+        }
+        RFragment::Empty => {
+          let blank = line.map_or("", |l| &l.indent);
+          write!(rv, "{blank}{line_ending}")?;
         }
-        RFragment::Empty => rv.push_str("\n"),
       }
     }
 
-    rv.push_str(&format!("{prefix}>\n", prefix = &prefix));
-    rv
+    write!(rv, "{close_indent}>")?;
+    if let Some(trailing) = close_line {
+      write!(rv, "{trailing}{line_ending}")?;
+    }
+    Ok(())
   }
 
-  fn value_list_to_string(values: &Vec<RValue>) -> String {
-    let mut rv = String::new();
-    for (i, value) in values.iter().enumerate() {
-      if i != 0 {
-        rv.push_str(" ");
-      }
-      rv.push_str(value.to_string().as_str());
+  fn write_value_list<W: fmt::Write + ?Sized>(rv: &mut W, values: &[RValue]) -> fmt::Result {
+    for value in values {
+      write!(rv, " {value}")?;
     }
-    rv
+    Ok(())
   }
 }
 
-#[derive(Debug, PartialEq)]
+/// A single value. Numbers keep the text they were parsed from (if any), which is written back
+/// as-is; equality compares numbers by value, across `I` and `F`.
+#[derive(Debug, Clone)]
 pub enum RValue<'a> {
   /// Quoted String
-  QS(String),
+  QS(Cow<'a, str>),
   /// Unquoted String
-  S(&'a str),
+  S(Cow<'a, str>),
   /// Integer
-  N(f64),
+  I(i64, Option<Cow<'a, str>>),
+  /// Float
+  F(f64, Option<Cow<'a, str>>),
+  /// `{GUID}`
+  G(Guid),
+}
+
+impl<'a, 'b> PartialEq<RValue<'b>> for RValue<'a> {
+  fn eq(&self, other: &RValue<'b>) -> bool {
+    match (self, other) {
+      (RValue::QS(a), RValue::QS(b)) => a == b,
+      (RValue::S(a), RValue::S(b)) => a == b,
+      (RValue::I(a, _), RValue::I(b, _)) => a == b,
+      (RValue::G(a), RValue::G(b)) => a == b,
+      (a, b) => matches!((a.get_num(), b.get_num()), (Some(a), Some(b)) if a == b),
+    }
+  }
 }
 
 pub struct RValues;
@@ -183,13 +576,17 @@ impl RValues {
   pub fn floats<'a, I: IntoIterator<Item=f64>>(values: I) -> Vec<RValue<'a>> {
     let mut rv = vec![];
     for v in values {
-      rv.push(RValue::N(v));
+      rv.push(RValue::F(v, None));
     }
     rv
   }
 
   pub fn ints<'a, I: IntoIterator<Item=i64>>(values: I) -> Vec<RValue<'a>> {
-    Self::floats(values.into_iter().map(|i| i as f64))
+    let mut rv = vec![];
+    for v in values {
+      rv.push(RValue::I(v, None));
+    }
+    rv
   }
 
   pub fn bools<'a, I: IntoIterator<Item=bool>>(values: I) -> Vec<RValue<'a>> {
@@ -199,7 +596,7 @@ impl RValues {
   pub fn strings<'a, I: IntoIterator<Item=&'a str>>(values: I) -> Vec<RValue<'a>> {
     let mut rv = vec![];
     for v in values {
-      rv.push(RValue::S(v.as_ref()));
+      rv.push(RValue::S(Cow::Borrowed(v)));
     }
     rv
   }
@@ -207,7 +604,7 @@ impl RValues {
   pub fn quoted_strings<'a, I: IntoIterator<Item=&'a str>>(values: I) -> Vec<RValue<'a>> {
     let mut rv = vec![];
     for v in values {
-      rv.push(RValue::QS(v.to_owned()));
+      rv.push(RValue::QS(Cow::Borrowed(v)));
     }
     rv
   }
@@ -224,76 +621,173 @@ impl RValues {
     Self::bools([b])
   }
 
-  pub fn string(s: &str) -> Vec<RValue> {
+  pub fn string(s: &str) -> Vec<RValue<'_>> {
     Self::strings([s])
   }
 
-  pub fn quoted_string(s: &str) -> Vec<RValue> {
+  pub fn quoted_string(s: &str) -> Vec<RValue<'_>> {
     Self::quoted_strings([s])
   }
+
+  pub fn guid<'a>(g: Guid) -> Vec<RValue<'a>> {
+    vec![RValue::G(g)]
+  }
 }
 
 impl<'a> RValue<'a> {
-  pub fn get_str(&'a self) -> Option<&'a str> {
+  pub fn into_owned(self) -> RValue<'static> {
+    match self {
+      RValue::QS(s) => RValue::QS(Cow::Owned(s.into_owned())),
+      RValue::S(s) => RValue::S(Cow::Owned(s.into_owned())),
+      RValue::I(i, raw) => RValue::I(i, raw.map(|raw| Cow::Owned(raw.into_owned()))),
+      RValue::F(f, raw) => RValue::F(f, raw.map(|raw| Cow::Owned(raw.into_owned()))),
+      RValue::G(g) => RValue::G(g),
+    }
+  }
+
+  pub fn get_str(&self) -> Option<&str> {
     match self {
-      RValue::QS(s) => Some(s.as_str()),
+      RValue::QS(s) => Some(s),
       RValue::S(s) => Some(s),
-      RValue::N(_) => None,
+      _ => None,
+    }
+  }
+
+  /// Any number, as a float.
+  pub fn get_num(&self) -> Option<f64> {
+    match self {
+      RValue::I(i, _) => Some(*i as f64),
+      RValue::F(f, _) => Some(*f),
+      _ => None,
     }
   }
 
-  pub fn get_num(&'a self) -> Option<f64> {
+  /// An integer, or a float without a fractional part.
+  pub fn get_i64(&self) -> Option<i64> {
     match self {
-      RValue::N(f) => Some(*f),
+      RValue::I(i, _) => Some(*i),
+      RValue::F(f, _) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => Some(*f as i64),
+      _ => None,
+    }
+  }
+
+  /// A number, nonzero meaning `true`.
+  pub fn get_bool(&self) -> Option<bool> {
+    self.get_num().map(|n| n != 0.0)
+  }
+
+  pub fn get_guid(&self) -> Option<Guid> {
+    match self {
+      RValue::G(g) => Some(*g),
       _ => None,
     }
   }
 }
 
-impl<'a> ToString for RValue<'a> {
-  fn to_string(&self) -> String {
+impl<'a> fmt::Display for RValue<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
-      RValue::QS(value) => {
-        let escape_seq = |c: char| ['\\', c].map(Some);
-        let value = value
-          .chars()
-          .flat_map(|c| match c {
-            '"' => escape_seq('"'),
-            '\t' => escape_seq('t'),
-            '\n' => escape_seq('n'),
-            '\\' => escape_seq('\\'),
-            c => [Some(c), None],
-          })
-          .filter_map(identity)
-          .collect::<String>();
-        format!("\"{value}\"", value = &value)
-      }
-      RValue::S(s) => s.to_string(),
-      RValue::N(n) => n.to_string(),
+      RValue::QS(value) => write_quoted(f, value),
+      RValue::S(s) if needs_quotes(s) => write_quoted(f, s),
+      RValue::S(s) => f.write_str(s),
+      RValue::I(_, Some(raw)) | RValue::F(_, Some(raw)) => f.write_str(raw),
+      RValue::I(i, None) => write!(f, "{i}"),
+      RValue::F(v, None) => write!(f, "{v}"),
+      RValue::G(g) => write!(f, "{g}"),
     }
   }
 }
 
+/// Whether an unquoted string would not parse back as a single token.
+fn needs_quotes(s: &str) -> bool {
+  s.is_empty() || s.starts_with(['"', '\'', '`']) || s.contains(char::is_whitespace)
+}
+
+/// Quotes `s` the way REAPER does: with `"` unless the string contains one, then `'`, then a
+/// backtick. Strings containing all three have their backticks replaced by `'`.
+fn write_quoted(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+  if !s.contains('"') {
+    write!(f, "\"{s}\"")
+  } else if !s.contains('\'') {
+    write!(f, "'{s}'")
+  } else if !s.contains('`') {
+    write!(f, "`{s}`")
+  } else {
+    write!(f, "`{}`", s.replace('`', "'"))
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
   #[test]
   fn test_element_str() {
-    let v = RValue::S("foo");
+    let v = RValue::S("foo".into());
     assert_eq!(v.get_str(), Some("foo"));
 
-    let v = RValue::N(5.0);
+    let v = RValue::F(5.0, None);
     assert_eq!(v.get_str(), None);
   }
 
+  #[test]
+  fn test_typed_attrs() {
+    let input = "<TRACK {E8B281C6-3542-394A-AAD4-A5875512F906}\n  PATTERN 2863311530 2863311529\n  SEL 1\n  VOL 0.25\n  TRACKID {E8B281C6-3542-394A-AAD4-A5875512F906}\n>\n";
+    let (_, track) = parse_element::<(_, nom::error::ErrorKind)>(input).unwrap();
+    let guid: Guid = "{E8B281C6-3542-394A-AAD4-A5875512F906}".parse().unwrap();
+
+    assert_matches!(&track.args[..], [RValue::G(g)] if *g == guid);
+    assert_eq!(track.get_i64_attr("PATTERN", 0), Some(2863311530));
+    assert_eq!(track.get_num_attr("PATTERN", 0), Some(2863311530.0));
+    assert_eq!(track.get_bool_attr("SEL", 0), Some(true));
+    assert_eq!(track.get_i64_attr("VOL", 0), None);
+    assert_eq!(track.get_num_attr("VOL", 0), Some(0.25));
+    assert_eq!(track.get_guid_attr("TRACKID", 0), Some(guid));
+    assert_eq!(track.get_str_attr("TRACKID", 0), None);
+    assert_eq!(track.to_string(), input);
+  }
+
+  #[test]
+  fn test_attr_tuples() {
+    let input = "<ITEM\n  VOLPAN 1 0.5 -1 -1 1\n  FADEIN 1 0.01 0\n  NAME \"bass take\"\n  MUTE 0 0\n  MUTE 1\n>\n";
+    let (_, item) = parse_element::<(_, nom::error::ErrorKind)>(input).unwrap();
+
+    assert_eq!(item.attr_values("VOLPAN").map(<[RValue]>::len), Some(5));
+    let (vol, pan): (f64, f64) = item.attr("VOLPAN").unwrap();
+    assert_eq!((vol, pan), (1.0, 0.5));
+
+    let fade: (i32, f64, f64, Option<i32>, Option<f64>) = item.attr("FADEIN").unwrap();
+    assert_eq!(fade, (1, 0.01, 0.0, None, None));
+
+    let (name,): (&str,) = item.attr("NAME").unwrap();
+    assert_eq!(name, "bass take");
+    assert_eq!(item.attr::<(bool, bool)>("MUTE"), Some((false, false)));
+    assert_eq!(item.attr_nth::<(bool, Option<bool>)>("MUTE", 1), Some((true, None)));
+    assert_eq!(item.attr::<Vec<f64>>("VOLPAN"), Some(vec![1.0, 0.5, -1.0, -1.0, 1.0]));
+
+    assert_eq!(item.attr::<(f64, f64, f64, f64)>("FADEIN"), None);
+    assert_eq!(item.attr::<(f64,)>("NAME"), None);
+    assert_eq!(item.attr::<(f64,)>("LENGTH"), None);
+  }
+
+  #[test]
+  fn test_args_as() {
+    let input = "<VST \"VST: ReaEQ (Cockos)\" reaeq.vst 0 \"\" 1919247729\n>\n";
+    let (_, vst) = parse_element::<(_, nom::error::ErrorKind)>(input).unwrap();
+    let (name, file, flag): (&str, &str, i32) = vst.args_as().unwrap();
+    assert_eq!((name, file, flag), ("VST: ReaEQ (Cockos)", "reaeq.vst", 0));
+  }
+
+  #[test]
+  fn test_number_equality() {
+    assert_eq!(RValue::I(1, Some("1".into())), RValue::F(1.0, None));
+    assert_ne!(RValue::I(1, None), RValue::S("1".into()));
+    assert_eq!(RValues::ints([3])[0].to_string(), "3");
+  }
+
   #[test]
   fn test_arg_not_exists() {
-    let v = RElement {
-      tag: "PROJECT",
-      args: vec![],
-      content: vec![],
-    };
+    let v = RElement::new("PROJECT", vec![]);
 
     assert_eq!(v.get_str_arg(0), None);
     assert_eq!(v.get_num_attr("FOO", 0), None);
@@ -302,12 +796,154 @@ mod tests {
   #[test]
   fn test_arg_wrong_type() {
     let v = RElement {
-      tag: "PROJECT",
-      args: vec![RValue::N(0.5)],
-      content: vec![RFragment::Attribute("FOO", vec![RValue::S("test")])],
+      tag: "PROJECT".into(),
+      args: vec![RValue::F(0.5, None)],
+      content: vec![RFragment::Attribute("FOO".into(), vec![RValue::S("test".into())])],
+      layout: None,
     };
 
     assert_eq!(v.get_str_arg(0), None);
     assert_eq!(v.get_num_attr("FOO", 0), None);
   }
+
+  fn track() -> RElement<'static> {
+    let mut track = RElement::new("TRACK", vec![]);
+    track.append_attribute("NAME", RValues::quoted_string("bass"));
+    track.append_attribute("PEAKCOL", RValues::int(16576));
+    track.append_child(RElement::new("ITEM", vec![]));
+    track.append_attribute("PEAKCOL", RValues::int(1));
+    track.append_child(RElement::new("FXCHAIN", vec![]));
+    track.append_child(RElement::new("ITEM", vec![]));
+    track
+  }
+
+  #[test]
+  fn test_set_attr() {
+    let mut track = track();
+    track.set_attr("NAME", RValues::quoted_string("lead"));
+    track.set_attr("NCHAN", RValues::int(4));
+
+    assert_eq!(track.get_str_attr("NAME", 0), Some("lead"));
+    assert_eq!(track.get_num_attr("NCHAN", 0), Some(4.0));
+    assert_matches!(track.content.last(), Some(RFragment::Attribute(name, _)) if name == "NCHAN");
+    assert_eq!(track.content.len(), 7);
+  }
+
+  #[test]
+  fn test_remove_attr_and_children() {
+    let mut track = track();
+    assert_eq!(track.remove_attr("PEAKCOL"), 2);
+    assert_eq!(track.remove_attr("PEAKCOL"), 0);
+
+    let items = track.remove_children_with_tag("ITEM");
+    assert_eq!(items.len(), 2);
+    assert_eq!(track.children_with_tag("ITEM").count(), 0);
+    assert_eq!(track.children_with_tag("FXCHAIN").count(), 1);
+  }
+
+  #[test]
+  fn test_insert_and_retain() {
+    let mut track = track();
+    track.insert_child_at(1, RElement::new("VOLENV2", vec![]));
+    assert_matches!(&track.content[1], RFragment::Child(c) if c.tag == "VOLENV2");
+
+    track.retain(|frag| matches!(frag, RFragment::Child(_)));
+    assert_eq!(track.content.len(), 4);
+  }
+
+  #[test]
+  fn test_children_with_tag_mut() {
+    let mut track = track();
+    for item in track.children_with_tag_mut("ITEM") {
+      item.set_attr("MUTE", RValues::ints([1, 0]));
+    }
+
+    assert!(track.children_with_tag("ITEM").all(|item| item.get_num_attr("MUTE", 0) == Some(1.0)));
+    assert_eq!(track.children_with_tag("FXCHAIN").next().unwrap().content.len(), 0);
+  }
+
+  #[test]
+  fn test_fragment_ids() {
+    let mut track = track();
+    track.append_bin_data("AAAA");
+
+    assert_eq!(track.position(&RFragmentId::Attribute("PEAKCOL")), Some(1));
+    assert_eq!(track.position(&RFragmentId::Child(1)), Some(4));
+    assert_eq!(track.position(&RFragmentId::BinData(0)), Some(6));
+    assert_eq!(track.position(&RFragmentId::Child(3)), None);
+
+    let removed = track.remove_fragment(&RFragmentId::Child(0));
+    assert_matches!(removed, Some(RFragment::Child(c)) if c.tag == "ITEM");
+
+    let replaced = track.replace_fragment(&RFragmentId::BinData(0), RFragment::BinData("BBBB".into()));
+    assert_matches!(replaced, Some(RFragment::BinData(data)) if data == "AAAA");
+    assert_matches!(track.fragment(&RFragmentId::BinData(0)), Some(RFragment::BinData(data)) if data == "BBBB");
+  }
+
+  #[test]
+  fn test_quoted_name_round_trip() {
+    let mut track = RElement::new("TRACK", vec![]);
+    track.set_attr("NAME", RValues::quoted_string(r#"Mike's "Bass" \ DI"#));
+    let written = track.to_string();
+    assert_eq!(written, "<TRACK\n NAME `Mike's \"Bass\" \\ DI`\n>\n");
+
+    let (_, parsed) = parse_element::<(_, nom::error::ErrorKind)>(&written).unwrap();
+    assert_eq!(parsed.get_str_attr("NAME", 0), Some(r#"Mike's "Bass" \ DI"#));
+  }
+
+  #[test]
+  fn test_unquoted_string_with_space_is_quoted() {
+    assert_eq!(RValue::S("two words".into()).to_string(), "\"two words\"");
+    assert_eq!(RValue::S("".into()).to_string(), "\"\"");
+    assert_eq!(RValue::S("UREI_Bass.wav".into()).to_string(), "UREI_Bass.wav");
+  }
+
+  #[test]
+  fn test_into_owned_outlives_input() {
+    let parsed = {
+      let input = String::from("<TRACK {E8B281C6-3542-394A-AAD4-A5875512F906}\n  NAME \"lead vox\"\n  <ITEM\n    LENGTH 2\n  >\n>");
+      let (_, element) = parse_element::<(_, nom::error::ErrorKind)>(&input).unwrap();
+      element.into_owned()
+    };
+
+    assert_eq!(parsed.get_str_attr("NAME", 0), Some("lead vox"));
+    assert_eq!(parsed.children_with_tag("ITEM").count(), 1);
+    assert!(parsed.to_string().starts_with("<TRACK {E8B281C6-3542-394A-AAD4-A5875512F906}\n"));
+  }
+
+  #[test]
+  fn test_owned_tree_is_send() {
+    fn assert_send<T: Send + 'static>(_: &T) {}
+
+    let element: RElement<'static> = RElement::new("TRACK", vec![]);
+    assert_send(&element);
+  }
+
+  #[test]
+  fn test_bin_data_bytes() {
+    let input = include_str!("../StreamingPlugin.rpp");
+    let project = crate::parse_chunk(input).unwrap();
+    let selector = "//VST".parse().unwrap();
+    let mut vst = project.select(&selector).next().unwrap().clone();
+
+    let bytes = vst.bin_data_bytes().unwrap();
+    let state = String::from_utf8_lossy(&bytes);
+    assert!(state.contains("{\"session_id\":null"));
+    assert!(state.contains("Program 1"));
+
+    let mut longer = bytes.clone();
+    longer.extend([0xAB; 300]);
+    vst.set_bin_data_bytes(&longer);
+    assert_eq!(vst.bin_data_bytes().unwrap(), longer);
+    let widths: Vec<_> = vst.content.iter().map(|frag| match frag {
+      RFragment::BinData(line) => line.len(),
+      _ => 0,
+    }).collect();
+    assert_eq!(widths[..widths.len() - 1], vec![128; widths.len() - 1]);
+
+    let mut cfg = RElement::new("RENDER_CFG", vec![]);
+    assert_eq!(cfg.bin_data_bytes().unwrap(), b"");
+    cfg.set_bin_data_bytes(b"evaw");
+    assert_matches!(&cfg.content[..], [RFragment::BinData(line)] if line == "ZXZhdw==");
+  }
 }
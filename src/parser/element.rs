@@ -1,97 +1,224 @@
-use std::collections::HashMap;
-
 use nom::{AsChar, InputTakeAtPosition, IResult};
 use nom::branch::alt;
-use nom::character::complete::{alphanumeric1, char, multispace0, multispace1};
-use nom::combinator::{map, success};
+use nom::character::complete::{char, multispace0};
+use nom::bytes::complete::take_till1;
+use nom::combinator::{cut, map, opt, success, verify};
 use nom::error::{ErrorKind, FromExternalError, ParseError};
-use nom::multi::fold_many0;
-use nom::number::complete::double;
-use nom::sequence::{delimited, preceded, terminated, tuple};
+use nom::sequence::{preceded, terminated, tuple};
 
-use crate::{RElement, RFragment, RFragmentId, RValue};
-use crate::parser::{number, values};
+use crate::{RElement, RFragment, RLayout, RLineLayout, RValue};
+use crate::parser::values;
 use crate::parser::whitespace::whitespace_line_ending;
 
 use super::identifier::parse_identifier;
-use super::quoted_string::parse_quoted_string;
 use super::whitespace::{multispace_no_newline_0, multispace_no_newline_1};
 
+/// How the lines of an element body that are not child elements are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BodyKind {
+  /// `NAME values...` attributes, with base64 lines told apart by their characters.
+  Attributes,
+  /// Plugin state, JSFX slider values, render settings or MIDI events: every line is kept as-is
+  /// in a [`RFragment::BinData`].
+  Data,
+}
+
+impl BodyKind {
+  /// The body of a `tag` element with `args`, nested in a `parent` element (`None` for the
+  /// outermost element).
+  pub fn of(parent: Option<&str>, tag: &str, args: &[RValue]) -> Self {
+    match (parent, tag) {
+      (_, "VST" | "AU" | "JS" | "RENDER_CFG") => BodyKind::Data,
+      (Some("CLAP" | "DX" | "LV2"), "STATE") => BodyKind::Data,
+      (_, "SOURCE") if args.first().and_then(RValue::get_str) == Some("MIDI") => BodyKind::Data,
+      _ => BodyKind::Attributes,
+    }
+  }
+}
+
+/// `NAME` followed by zero or more values.
 pub(crate) fn parse_attribute<'a, E>(input: &'a str) -> IResult<&'a str, RFragment<'a>, E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
   map(
-    tuple((terminated(parse_identifier, multispace_no_newline_1), values::parse_value_list)),
-    |(id, values)| RFragment::Attribute(id, values),
+    tuple((
+      parse_identifier,
+      map(opt(preceded(multispace_no_newline_1, values::parse_value_list)), Option::unwrap_or_default),
+    )),
+    |(id, values)| RFragment::Attribute(id.into(), values),
   )(input)
 }
 
-pub(crate) fn parse_child_element<'a, E>(input: &'a str) -> IResult<&'a str, RFragment<'a>, E>
+pub fn parse_bin_data_body<T, E: ParseError<T>>(input: T) -> IResult<T, T, E>
+  where
+    T: InputTakeAtPosition,
+    <T as InputTakeAtPosition>::Item: AsChar + Clone,
+{
+  input.split_at_position1_complete(
+    |item| {
+      let c = item.as_char();
+      !(c.is_alpha() || c.is_ascii_hexdigit() || c == '=' || c == '+' || c == '/')
+    },
+    ErrorKind::AlphaNumeric,
+  )
+}
+
+pub(crate) fn parse_bin_data<'a, E>(input: &'a str) -> IResult<&'a str, RFragment<'a>, E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-  map(parse_element, RFragment::Child)(input)
+  map(parse_bin_data_body, |s: &'a str| RFragment::BinData(s.into()))(input)
 }
 
-pub fn parse_bin_data_body<T, E: ParseError<T>>(input: T) -> IResult<T, T, E>
+/// A whole line of a [`BodyKind::Data`] body, unless it closes the element.
+pub(crate) fn parse_data_line<'a, E>(input: &'a str) -> IResult<&'a str, RFragment<'a>, E>
   where
-    T: InputTakeAtPosition,
-    <T as InputTakeAtPosition>::Item: AsChar + Clone,
+    E: ParseError<&'a str>,
 {
-  input.split_at_position_complete(|item| {
-    let c = item.as_char();
-    !(c.is_alpha() || c.is_digit(16) || c == '=' || c == '+' || c == '/')
-  })
+  map(
+    verify(take_till1(|c| c == '\r' || c == '\n'), |line: &str| {
+      !line.starts_with('>') && !line.trim_end().is_empty()
+    }),
+    |line: &'a str| RFragment::BinData(line.trim_end().into()),
+  )(input)
 }
 
-pub(crate) fn parse_bin_data<'a, E>(input: &'a str) -> IResult<&'a str, RFragment<'a>, E>
+/// One body line that is not a child element, including its line ending.
+fn parse_line_fragment<'a, E>(kind: BodyKind) -> impl FnMut(&'a str) -> IResult<&'a str, RFragment<'a>, E>
+  where
+    E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
+{
+  move |input| {
+    let empty = map(success(""), |_| RFragment::Empty);
+    match kind {
+      BodyKind::Attributes => alt((
+        terminated(parse_attribute, whitespace_line_ending),
+        terminated(parse_bin_data, whitespace_line_ending),
+        terminated(empty, whitespace_line_ending),
+      ))(input),
+      BodyKind::Data => alt((
+        terminated(parse_data_line, whitespace_line_ending),
+        terminated(empty, whitespace_line_ending),
+      ))(input),
+    }
+  }
+}
+
+/// What a line of an element body holds.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BodyLine<'a> {
+  /// `<TAG args...`, opening a child element. The end of the line is left unparsed.
+  Open(&'a str, Vec<RValue<'a>>),
+  /// `>`, closing the innermost open element. The rest of the line is left unparsed.
+  Close,
+  /// Any other line, including its line ending.
+  Fragment(RFragment<'a>),
+}
+
+/// The tag and arguments of an element.
+pub(crate) type Header<'a> = (&'a str, Vec<RValue<'a>>);
+
+/// `<TAG args...`, up to but not including the end of the line.
+pub(crate) fn parse_header<'a, E>(input: &'a str) -> IResult<&'a str, Header<'a>, E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-  map(parse_bin_data_body, |s: &str| RFragment::BinData(s.to_string()))(input)
+  preceded(char('<'), cut(tuple((terminated(parse_identifier, multispace_no_newline_0), values::parse_value_list))))(input)
 }
 
-pub(crate) fn parse_element_fragment<'a, E>(input: &'a str) -> IResult<&'a str, RFragment<'a>, E>
+/// One line of a body of the given kind, returning its indentation along with what it holds.
+/// Every reader of element bodies goes through this, so that they agree on the grammar.
+pub(crate) fn parse_body_line<'a, E>(kind: BodyKind) -> impl FnMut(&'a str) -> IResult<&'a str, (&'a str, BodyLine<'a>), E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-  preceded(
+  tuple((
     multispace_no_newline_0,
-    terminated(
-      alt((
-        parse_child_element,
-        parse_attribute,
-        parse_bin_data,
-        map(success(""), |_| RFragment::Empty),
-      )),
-      whitespace_line_ending,
-    ),
-  )(input)
+    alt((
+      map(char('>'), |_| BodyLine::Close),
+      map(parse_header, |(tag, args)| BodyLine::Open(tag, args)),
+      map(parse_line_fragment(kind), BodyLine::Fragment),
+    )),
+  ))
+}
+
+/// The whitespace at the end of `line`, before its line ending.
+pub(crate) fn trailing_whitespace(line: &str) -> &str {
+  let line = line.trim_end_matches(['\r', '\n']);
+  &line[line.trim_end().len()..]
+}
+
+/// The part of `input` in front of `rest`, which must be a suffix of it.
+pub(crate) fn consumed<'a>(input: &'a str, rest: &str) -> &'a str {
+  &input[..input.len() - rest.len()]
 }
 
-pub(crate) fn parse_element_body<'a, E>(input: &'a str) -> IResult<&'a str, RElement<'a>, E>
+/// The rest of an element whose `<TAG args` has been read from the start of `line`, up to and
+/// including its closing `>`.
+fn parse_element_rest<'a, E>(line: &'a str, input: &'a str, parent: Option<&str>, tag: &'a str, args: Vec<RValue<'a>>) -> IResult<&'a str, RElement<'a>, E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-  map(
-    tuple((
-      terminated(parse_identifier, multispace_no_newline_0),
-      terminated(values::parse_value_list, whitespace_line_ending),
-      fold_many0(parse_element_fragment, RElement::default, |mut element, fragment| {
+  let kind = BodyKind::of(parent, tag, &args);
+  let (mut input, line_ending) = cut(whitespace_line_ending)(input)?;
+  let mut element = RElement::new(tag, args);
+  let mut layout = RLayout {
+    header_trailing: trailing_whitespace(consumed(line, input)).into(),
+    line_ending: line_ending.into(),
+    ..RLayout::default()
+  };
+
+  loop {
+    let (rest, (indent, body_line)) = match parse_body_line(kind)(input) {
+      Ok(parsed) => parsed,
+      Err(nom::Err::Error(_)) => {
+        let (line_start, _) = multispace0(input)?;
+        return Err(nom::Err::Failure(E::from_char(line_start, '>')));
+      }
+      Err(e) => return Err(e),
+    };
+    let line = &input[indent.len()..];
+    input = rest;
+    let trailing = match body_line {
+      BodyLine::Close => {
+        layout.close_indent = indent.into();
+        break;
+      }
+      BodyLine::Open(tag, args) => {
+        let (rest, mut child) = parse_element_rest(line, input, Some(element.tag.as_ref()), tag, args)?;
+        input = cut(whitespace_line_ending)(rest)?.0;
+        layout.body_indent.get_or_insert(indent.into());
+        if let Some(child_layout) = child.layout.as_mut() {
+          child_layout.indent = indent.into();
+        }
+        element.content.push(RFragment::Child(child));
+        trailing_whitespace(consumed(rest, input))
+      }
+      BodyLine::Fragment(fragment) => {
+        if !matches!(fragment, RFragment::Empty) {
+          layout.body_indent.get_or_insert(indent.into());
+        }
         element.content.push(fragment);
-        element
-      }),
-    )),
-    |(tag, args, element)| RElement { tag, args, ..element },
-  )(input)
+        trailing_whitespace(consumed(line, input))
+      }
+    };
+    layout.lines.push(Some(RLineLayout {
+      indent: indent.into(),
+      trailing: trailing.into(),
+    }));
+  }
+
+  element.layout = Some(layout);
+  Ok((input, element))
 }
 
 pub fn parse_element<'a, E>(input: &'a str) -> IResult<&'a str, RElement<'a>, E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-  delimited(char('<'), parse_element_body, tuple((multispace0, char('>'))))(input)
+  let (rest, (tag, args)) = parse_header(input)?;
+  parse_element_rest(input, rest, None, tag, args)
 }
 
 #[cfg(test)]
@@ -102,7 +229,7 @@ mod test {
 
   use super::*;
 
-  fn contains_attribute(v: &Vec<RFragment>, name: &str) -> bool {
+  fn contains_attribute(v: &[RFragment], name: &str) -> bool {
     v.iter().filter_map(is_fragment_attribute(name)).next().is_some()
   }
 
@@ -118,7 +245,7 @@ mod test {
   fn simple_tag() {
     assert_matches!(
             parse_element::<(&'static str, ErrorKind)>("<RENDER_CFG\n>"),
-            Ok(("", RElement { tag: "RENDER_CFG", .. }))
+            Ok(("", RElement { tag, .. })) if tag == "RENDER_CFG"
         );
   }
 
@@ -129,11 +256,11 @@ mod test {
             Ok((
                 "",
                 RElement {
-                    tag: "METRONOME",
+                    tag,
                     args,
                     ..
                 }
-            )) if matches!(&args[..], &[RValue::N(6.0), RValue::N(2.0)])
+            )) if tag == "METRONOME" && matches!(&args[..], &[RValue::I(6, _), RValue::I(2, _)])
         );
   }
 
@@ -152,21 +279,101 @@ mod test {
             Ok((
                 "",
                 RElement {
-                    tag: "METRONOME",
+                    tag,
                     args,
                     content,
                     ..
                 }
-            )) if matches!(&args[..], &[RValue::N(6.0), RValue::N(2.0)]) && contains_attribute(&content, "VOL") && contains_attribute(&content, "MULT")
+            )) if tag == "METRONOME" && matches!(&args[..], &[RValue::I(6, _), RValue::I(2, _)]) && contains_attribute(&content, "VOL") && contains_attribute(&content, "MULT")
         );
   }
 
+  #[test]
+  fn test_lossless_numbers_and_indent() {
+    let input = "<ITEM\r\n    POSITION 1e-05\r\n    FADEIN 1 0.010 0 1\r\n\r\n    <SOURCE WAVE\r\n      FILE \"a.wav\"\r\n    >\r\n  >\r\n";
+    let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_eq!(parsed.to_string(), input);
+    assert_eq!(parsed.get_num_attr("POSITION", 0), Some(0.00001));
+    assert_eq!(parsed.to_string_with_indent(0), "<ITEM\n POSITION 1e-05\n FADEIN 1 0.010 0 1\n\n <SOURCE WAVE\n  FILE \"a.wav\"\n >\n>\n");
+  }
+
+  #[test]
+  fn test_lossless_inserted_child_follows_parent() {
+    let input = "<TRACK\r\n  NAME bass\r\n>\r\n";
+    let mut parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    let mut item = RElement::new("ITEM", vec![]);
+    item.append_attribute("LENGTH", crate::RValues::float(2.5));
+    parsed.append_child(item);
+    assert_eq!(parsed.to_string(), "<TRACK\r\n  NAME bass\r\n  <ITEM\r\n    LENGTH 2.5\r\n  >\r\n>\r\n");
+  }
+
+  #[test]
+  fn test_valueless_attribute() {
+    let input = "<TRACK\n  LOCK\n  AAAA\n  3VpEA+5e7f4=\n  NAME x\n>\n";
+    let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_matches!(&parsed.content[0], RFragment::Attribute(name, values) if name == "LOCK" && values.is_empty());
+    assert_matches!(&parsed.content[1], RFragment::Attribute(name, values) if name == "AAAA" && values.is_empty());
+    assert_matches!(&parsed.content[2], RFragment::BinData(data) if data == "3VpEA+5e7f4=");
+    assert_eq!(parsed.get_str_attr("NAME", 0), Some("x"));
+    assert_eq!(parsed.to_string(), input);
+  }
+
+  #[test]
+  fn test_data_bodies() {
+    let input = "<VST \"VST: ReaEQ (Cockos)\" reaeq.vst 0 \"\" 1919247729\n  AAAA\n  AFByb2dyYW0gMQAQAAAA\n>\n";
+    let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_matches!(&parsed.content[..], [RFragment::BinData(a), RFragment::BinData(b)] if a == "AAAA" && b == "AFByb2dyYW0gMQAQAAAA");
+    assert_eq!(parsed.to_string(), input);
+  }
+
+  #[test]
+  fn test_plugin_state_children() {
+    let input = "<CLAP \"CLAP: Diva (u-he)\" com.u-he.Diva \"\"\n  CFG 4 760 300 \"\"\n  <STATE\n    AAAA\n    ZGl2YQ==\n  >\n>\n";
+    let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert!(contains_attribute(&parsed.content, "CFG"));
+    let state = parsed.children_with_tag("STATE").next().unwrap();
+    assert_matches!(&state.content[..], [RFragment::BinData(a), RFragment::BinData(b)] if a == "AAAA" && b == "ZGl2YQ==");
+    assert_eq!(parsed.to_string(), input);
+
+    let track_state = parse_element::<(_, ErrorKind)>("<TRACK
+  <STATE
+    AAAA
+  >
+>
+").unwrap().1;
+    assert_matches!(&track_state.children_with_tag("STATE").next().unwrap().content[0], RFragment::Attribute(name, _) if name == "AAAA");
+  }
+
+  #[test]
+  fn test_js_slider_line() {
+    let input = "<JS utility/volume \"\"\n  - 0 -6.5 - - \"\"\n>\n";
+    let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_matches!(&parsed.content[..], [RFragment::BinData(line)] if line == "- 0 -6.5 - - \"\"");
+    assert_eq!(parsed.to_string(), input);
+  }
+
+  #[test]
+  fn test_midi_source_events() {
+    let input = "<SOURCE MIDI\n  HASDATA 1 960 QN\n  E 480 90 3c 60\n  <X 0 0\n    8AB+AAAA\n  >\n  E 480 80 3c 00\n>\n";
+    let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_matches!(&parsed.content[0], RFragment::BinData(line) if line == "HASDATA 1 960 QN");
+    assert_matches!(&parsed.content[1], RFragment::BinData(line) if line == "E 480 90 3c 60");
+    assert_eq!(parsed.children_with_tag("X").count(), 1);
+    assert_eq!(parsed.to_string(), input);
+
+    let wave = parse_element::<(_, ErrorKind)>("<SOURCE WAVE\n  FILE \"a.wav\"\n>\n").unwrap().1;
+    assert_eq!(wave.get_str_attr("FILE", 0), Some("a.wav"));
+  }
+
   #[test]
   fn test_fx_no_bin() {
     let input = r#"<VST "VST: Streaming Plugin (Distopik)" StreamingPlugin.vst 0 "" 54811357<56535403445ADD73747265616D696E67> ""
         >
         "#;
     let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_eq!(parsed.tag, "VST");
+    assert_eq!(parsed.args.len(), 6);
+    assert!(parsed.content.is_empty());
   }
 
   #[test]
@@ -187,5 +394,7 @@ mod test {
     WAK 0 0
   >"#;
     let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+    assert_eq!(parsed.children_with_tag("VST").count(), 1);
+    assert_eq!(parsed.get_str_attr("PRESETNAME", 0), Some("Program 1"));
   }
 }
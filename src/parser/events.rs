@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use nom::error::ParseError;
+use nom::Offset;
+
+use crate::error::ParseFailure;
+use crate::parser::element::{parse_body_line, parse_header, BodyKind, BodyLine};
+use crate::parser::whitespace::{multispace_no_newline_0, whitespace_line_ending};
+use crate::{Error, RFragment, RValue};
+
+/// One step of an element tree, as produced by [`Events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+  /// `<TAG args...`
+  StartElement { tag: Cow<'a, str>, args: Vec<RValue<'a>> },
+  /// `NAME values...`
+  Attribute { name: Cow<'a, str>, values: Vec<RValue<'a>> },
+  /// A line of base64 data, or a line of a plugin state, render settings or MIDI body.
+  BinData(Cow<'a, str>),
+  /// The `>` closing the innermost open element.
+  EndElement,
+}
+
+/// Pull parser over a single element that yields [`Event`]s instead of building an
+/// [`RElement`](crate::RElement) tree, along with the byte range of the input each event was read
+/// from. Blank lines are skipped, and the first error ends the iteration.
+pub struct Events<'a> {
+  input: &'a str,
+  pos: usize,
+  /// Tag and body kind of each open element, outermost first.
+  stack: Vec<(&'a str, BodyKind)>,
+  started: bool,
+  done: bool,
+}
+
+impl<'a> Events<'a> {
+  pub fn new(input: &'a str) -> Self {
+    Self {
+      input,
+      pos: 0,
+      stack: vec![],
+      started: false,
+      done: false,
+    }
+  }
+
+  fn span(&self, from: &'a str, to: &'a str) -> Range<usize> {
+    self.input.offset(from)..self.input.offset(to)
+  }
+
+  fn fail(&self, failure: nom::Err<ParseFailure>) -> Error {
+    match failure {
+      nom::Err::Error(failure) | nom::Err::Failure(failure) => Error::from_failure(self.input, failure),
+      nom::Err::Incomplete(_) => Error::at(self.input, self.input.len(), "unexpected end of input"),
+    }
+  }
+
+  /// Opens the element whose `<TAG args` runs from `start` to `after`.
+  fn start_element(&mut self, start: &'a str, after: &'a str, tag: &'a str, args: Vec<RValue<'a>>) -> Result<(Event<'a>, Range<usize>), Error> {
+    let header = start[..start.offset(after)].trim_end();
+    let span = self.span(start, &start[header.len()..]);
+    let (after, _) = whitespace_line_ending::<ParseFailure>(after).map_err(|e| self.fail(e))?;
+    let kind = BodyKind::of(self.stack.last().map(|&(parent, _)| parent), tag, &args);
+    self.stack.push((tag, kind));
+    self.pos = self.input.offset(after);
+    Ok((Event::StartElement { tag: tag.into(), args }, span))
+  }
+
+  fn read_event(&mut self) -> Result<Option<(Event<'a>, Range<usize>)>, Error> {
+    if !self.started {
+      self.started = true;
+      let rest = self.input.trim_start();
+      let (after, (tag, args)) = parse_header::<ParseFailure>(rest).map_err(|e| self.fail(e))?;
+      return self.start_element(rest, after, tag, args).map(Some);
+    }
+
+    let Some(&(_, kind)) = self.stack.last() else {
+      let trailing = self.input[self.pos..].trim_start();
+      if trailing.is_empty() {
+        return Ok(None);
+      }
+      return Err(Error::at(self.input, self.input.offset(trailing), "unexpected content after the closing `>`"));
+    };
+
+    loop {
+      let rest = &self.input[self.pos..];
+      let (after, (indent, line)) = match parse_body_line::<ParseFailure>(kind)(rest) {
+        Ok(parsed) => parsed,
+        Err(nom::Err::Error(_)) => {
+          let line_start = multispace_no_newline_0::<_, ParseFailure>(rest).map_or("", |(line_start, _)| line_start);
+          return Err(Error::from_failure(self.input, ParseFailure::from_char(line_start, '>')));
+        }
+        Err(e) => return Err(self.fail(e)),
+      };
+      let start = &rest[indent.len()..];
+      match line {
+        BodyLine::Close => {
+          self.stack.pop();
+          let after = if self.stack.is_empty() {
+            after
+          } else {
+            whitespace_line_ending::<ParseFailure>(after).map_err(|e| self.fail(e))?.0
+          };
+          self.pos = self.input.offset(after);
+          return Ok(Some((Event::EndElement, self.span(start, &start[1..]))));
+        }
+        BodyLine::Open(tag, args) => return self.start_element(start, after, tag, args).map(Some),
+        BodyLine::Fragment(fragment) => {
+          self.pos = self.input.offset(after);
+          let line = &start[..start.offset(after)];
+          let span = self.span(start, &start[line.trim_end().len()..]);
+          match fragment {
+            RFragment::Attribute(name, values) => return Ok(Some((Event::Attribute { name, values }, span))),
+            RFragment::BinData(data) => return Ok(Some((Event::BinData(data), span))),
+            _ => continue,
+          }
+        }
+      }
+    }
+  }
+}
+
+impl<'a> Iterator for Events<'a> {
+  type Item = Result<(Event<'a>, Range<usize>), Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let rv = self.read_event();
+    if !matches!(rv, Ok(Some(_))) {
+      self.done = true;
+    }
+    rv.transpose()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::parser::parse_chunk;
+
+  use super::*;
+
+  #[test]
+  fn events_with_spans() {
+    let input = "<TRACK {ABC}\n  NAME \"bass\"\n\n  <ITEM\n    POSITION 1.5\n    ZmFk+w==\n  >\n>\n";
+    let events: Vec<_> = Events::new(input).collect::<Result<_, _>>().unwrap();
+
+    let texts: Vec<_> = events.iter().map(|(_, span)| &input[span.clone()]).collect();
+    assert_eq!(texts, ["<TRACK {ABC}", "NAME \"bass\"", "<ITEM", "POSITION 1.5", "ZmFk+w==", ">", ">"]);
+    assert_matches!(&events[0].0, Event::StartElement { tag, args } if tag == "TRACK" && args.len() == 1);
+    assert_matches!(&events[1].0, Event::Attribute { name, values } if name == "NAME" && values[0].get_str() == Some("bass"));
+    assert_matches!(&events[4].0, Event::BinData(data) if data == "ZmFk+w==");
+    assert_matches!(events[6].0, Event::EndElement);
+
+    let input = "<LV2 \"LV2: Calf Reverb\" \"\"\n  <STATE\n    AAAA\n  >\n>\n";
+    let events: Vec<_> = Events::new(input).map(|e| e.unwrap().0).collect();
+    assert_eq!(events[2], Event::BinData("AAAA".into()));
+  }
+
+  #[test]
+  fn plugin_inventory() {
+    let input = include_str!("../../StreamingPlugin.rpp");
+    let mut plugins = vec![];
+    let mut depth = 0;
+    for event in Events::new(input) {
+      match event.unwrap().0 {
+        Event::StartElement { tag, args } => {
+          depth += 1;
+          if tag == "VST" {
+            plugins.push(args[0].get_str().unwrap().to_string());
+          }
+        }
+        Event::EndElement => depth -= 1,
+        _ => {}
+      }
+    }
+    assert_eq!(depth, 0);
+
+    let tree = parse_chunk(input).unwrap();
+    let vsts = tree.select(&"//VST".parse().unwrap()).count();
+    assert_eq!(plugins.len(), vsts);
+  }
+
+  #[test]
+  fn errors_match_tree_parser() {
+    let input = "<REAPER_PROJECT 0.1\n  <TRACK\n  >\n  <TRACK\n    <FXCHAIN\n      SHOW 0\n      }{ oops\n    >\n  >\n>\n";
+    let error = Events::new(input).find_map(Result::err).unwrap();
+    assert_eq!(error, parse_chunk(input).unwrap_err());
+
+    let mut events = Events::new("<TRACK\n  NAME x\n");
+    assert!(events.by_ref().take(2).all(|e| e.is_ok()));
+    assert_eq!(events.next().unwrap().unwrap_err().reason, "unexpected end of input, expected `>`");
+    assert!(events.next().is_none());
+  }
+}
@@ -7,11 +7,7 @@ where
 {
     input.split_at_position1_complete(
         |item| {
-            !(match (item, item.is_alpha() | item.is_numeric()) {
-                ('_', _) => true,
-                (_, true) => true,
-                _ => false,
-            })
+            !matches!((item, item.is_alpha() | item.is_numeric()), ('_', _) | (_, true))
         },
         ErrorKind::IsNot,
     )
@@ -0,0 +1,136 @@
+use nom::branch::alt;
+use nom::bytes::complete::{escaped, tag};
+use nom::character::complete::{char, multispace0, none_of, one_of};
+use nom::combinator::{cut, opt, recognize};
+use nom::error::ParseError;
+use nom::multi::separated_list0;
+use nom::number::complete::recognize_float;
+use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::IResult;
+
+/// The text of a JSON string literal, including the quotes.
+fn json_string<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+  recognize(delimited(
+    char('"'),
+    opt(escaped(none_of("\\\""), '\\', one_of("\"\\/bfnrtu"))),
+    cut(char('"')),
+  ))(input)
+}
+
+fn json_array<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+  recognize(delimited(
+    terminated(char('['), multispace0),
+    separated_list0(delimited(multispace0, char(','), multispace0), json_value),
+    cut(preceded(multispace0, char(']'))),
+  ))(input)
+}
+
+fn json_value<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+  alt((
+    json_string,
+    recognize_float,
+    tag("null"),
+    tag("true"),
+    tag("false"),
+    recognize(json_object),
+    json_array,
+  ))(input)
+}
+
+/// The members of a JSON object, with each value kept as the text it was written as.
+pub(crate) fn json_object<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Vec<(&'a str, &'a str)>, E> {
+  delimited(
+    terminated(char('{'), multispace0),
+    separated_list0(
+      delimited(multispace0, char(','), multispace0),
+      separated_pair(json_string, delimited(multispace0, char(':'), multispace0), json_value),
+    ),
+    cut(preceded(multispace0, char('}'))),
+  )(input)
+}
+
+/// The string a JSON string literal stands for, or `None` if `text` is not a valid one.
+pub(crate) fn unescape_json_string(text: &str) -> Option<String> {
+  let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+  let mut rv = String::with_capacity(inner.len());
+  let mut chars = inner.chars();
+  let hex4 = |chars: &mut std::str::Chars| -> Option<u32> {
+    let hex: String = chars.by_ref().take(4).collect();
+    u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 4)
+  };
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      rv.push(c);
+      continue;
+    }
+    let escaped = match chars.next()? {
+      'b' => '\u{8}',
+      'f' => '\u{c}',
+      'n' => '\n',
+      'r' => '\r',
+      't' => '\t',
+      'u' => {
+        let high = hex4(&mut chars)?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+          let rest = chars.as_str().strip_prefix("\\u")?;
+          chars = rest.chars();
+          let low = hex4(&mut chars)?;
+          0x10000 + ((high - 0xD800) << 10) + low.checked_sub(0xDC00)?
+        } else {
+          high
+        };
+        char::from_u32(code)?
+      }
+      c => c,
+    };
+    rv.push(escaped);
+  }
+  Some(rv)
+}
+
+pub(crate) fn escape_json_string(s: &str) -> String {
+  let mut rv = String::with_capacity(s.len() + 2);
+  rv.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => rv.push_str("\\\""),
+      '\\' => rv.push_str("\\\\"),
+      '\n' => rv.push_str("\\n"),
+      '\r' => rv.push_str("\\r"),
+      '\t' => rv.push_str("\\t"),
+      c if c.is_control() => rv.push_str(&format!("\\u{:04x}", c as u32)),
+      c => rv.push(c),
+    }
+  }
+  rv.push('"');
+  rv
+}
+
+#[cfg(test)]
+mod test {
+  use nom::error::ErrorKind;
+
+  use super::*;
+
+  #[test]
+  fn members_keep_raw_values() {
+    let input = r#"{"session_id":null, "rate" : 4.8e4,"tags":["a", {"b":[]}],"name":"x\"y"}"#;
+    let (rest, members) = json_object::<(_, ErrorKind)>(input).unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(
+      members,
+      [("\"session_id\"", "null"), ("\"rate\"", "4.8e4"), ("\"tags\"", r#"["a", {"b":[]}]"#), ("\"name\"", r#""x\"y""#)]
+    );
+    assert!(json_object::<(_, ErrorKind)>(r#"{"a":}"#).is_err());
+    assert!(json_object::<(_, ErrorKind)>(r#"{"a":1"#).is_err());
+  }
+
+  #[test]
+  fn strings() {
+    for s in ["", "plain", "quote \" and \\ back", "tab\tnew\nline", "\u{1}", "ünïcödé 🎵"] {
+      assert_eq!(unescape_json_string(&escape_json_string(s)).as_deref(), Some(s));
+    }
+    assert_eq!(unescape_json_string(r#""\u00e9\ud83c\udfb5\/""#).as_deref(), Some("é🎵/"));
+    assert_eq!(unescape_json_string(r#""\ud83c""#), None);
+  }
+}
@@ -1,13 +1,203 @@
 pub use element::parse_element;
+pub(crate) use element::{BodyKind, BodyLine};
+pub use events::{Event, Events};
+pub use recover::Recovered;
+pub(crate) use json::{escape_json_string, unescape_json_string};
+
+use crate::error::ParseFailure;
+use crate::select::Selector;
+use crate::{Error, Project, RElement, RValue};
 
 mod element;
+mod events;
 mod identifier;
+mod json;
 mod number;
 mod quoted_string;
+mod recover;
+mod selector;
 mod string;
 mod values;
 mod whitespace;
 
+/// Parses a single element, such as a chunk from `GetTrackStateChunk`. Surrounding whitespace is
+/// allowed; anything else after the closing `>` is an error.
+pub fn parse_chunk(input: &str) -> Result<RElement<'_>, Error> {
+    let start = input.len() - input.trim_start().len();
+    match parse_element::<ParseFailure>(&input[start..]) {
+        Ok((rest, mut element)) => {
+            let trailing = rest.trim_start();
+            if trailing.is_empty() {
+                record_surroundings(&mut element, &input[..start], rest);
+                Ok(element)
+            } else {
+                Err(Error::at(input, input.len() - trailing.len(), "unexpected content after the closing `>`"))
+            }
+        }
+        Err(nom::Err::Error(failure) | nom::Err::Failure(failure)) => Err(Error::from_failure(input, failure)),
+        Err(nom::Err::Incomplete(_)) => Err(Error::at(input, input.len(), "unexpected end of input")),
+    }
+}
+
+/// Records the whitespace before and after the outermost element of a chunk in its layout.
+fn record_surroundings<'a>(element: &mut RElement<'a>, before: &'a str, after: &'a str) {
+    if let Some(layout) = element.layout.as_mut() {
+        layout.indent = before.into();
+        layout.end = Some(after.into());
+    }
+}
+
+/// Parses one line of the body of an element, e.g. `  NAME "bass"\n`. Only the header of a child
+/// element and the `>` of a closing line are read, not the rest of their line.
+pub(crate) fn parse_body_line(line: &str, kind: BodyKind) -> Result<BodyLine<'_>, Error> {
+    match element::parse_body_line::<ParseFailure>(kind)(line) {
+        Ok((rest, (_, BodyLine::Fragment(_)))) if !rest.is_empty() => {
+            Err(Error::at(line, line.len() - rest.len(), "expected a single line"))
+        }
+        Ok((_, (_, body_line))) => Ok(body_line),
+        Err(nom::Err::Error(failure) | nom::Err::Failure(failure)) => Err(Error::from_failure(line, failure)),
+        Err(nom::Err::Incomplete(_)) => Err(Error::at(line, line.len(), "unexpected end of input")),
+    }
+}
+
+/// Parses a whitespace-separated list of values, e.g. a line of a [`BodyKind::Data`] body.
+///
+/// [`BodyKind::Data`]: element::BodyKind::Data
+pub(crate) fn parse_values(input: &str) -> Result<Vec<RValue<'_>>, Error> {
+    let start = input.len() - input.trim_start().len();
+    match values::parse_value_list::<ParseFailure>(&input[start..]) {
+        Ok((rest, values)) if rest.trim().is_empty() => Ok(values),
+        Ok((rest, _)) => Err(Error::at(input, input.len() - rest.len(), "expected a value")),
+        Err(nom::Err::Error(failure) | nom::Err::Failure(failure)) => Err(Error::from_failure(input, failure)),
+        Err(nom::Err::Incomplete(_)) => Err(Error::at(input, input.len(), "unexpected end of input")),
+    }
+}
+
+/// Parses the contents of an RPP file.
+pub fn parse_project(input: &str) -> Result<Project<'_>, Error> {
+    let element = parse_chunk(input)?;
+    if element.tag != "REAPER_PROJECT" {
+        let start = input.len() - input.trim_start().len();
+        return Err(Error::at(input, start, format!("expected a REAPER_PROJECT element, found {}", element.tag)));
+    }
+    Ok(Project(element))
+}
+
+/// Like [`parse_chunk`], but lines that cannot be parsed are kept as
+/// [`RFragment::Raw`](crate::RFragment::Raw) and reported as diagnostics, and elements left open
+/// at the end of the input are closed. Fails only if the input does not start with an element.
+pub fn parse_chunk_recovering(input: &str) -> Result<Recovered<RElement<'_>>, Error> {
+    let start = input.len() - input.trim_start().len();
+    let mut recovery = recover::Recovery::new(input);
+    let (rest, mut element) = match recovery.element(&input[start..]) {
+        Some(parsed) => parsed,
+        None => return Err(Error::at(input, start, "expected `<` followed by an element tag")),
+    };
+
+    let trailing = rest.trim_start();
+    if !trailing.is_empty() {
+        recovery.diagnostics.push(Error::at(input, input.len() - trailing.len(), "unexpected content after the closing `>`"));
+    }
+    record_surroundings(&mut element, &input[..start], &rest[..rest.len() - trailing.len()]);
+
+    Ok(Recovered {
+        parsed: element,
+        diagnostics: recovery.diagnostics,
+    })
+}
+
+pub(crate) fn parse_selector(input: &str) -> Result<Selector, Error> {
+    match selector::parse_selector_body::<ParseFailure>(input) {
+        Ok((_, selector)) => Ok(selector),
+        Err(nom::Err::Error(failure) | nom::Err::Failure(failure)) => Err(Error::from_failure(input, failure)),
+        Err(nom::Err::Incomplete(_)) => Err(Error::at(input, input.len(), "unexpected end of selector")),
+    }
+}
+
+/// Parses a JSON object, such as a configuration embedded in a plugin state, into its members.
+/// Keys are unescaped; values are kept as the text they were written as.
+pub(crate) fn parse_json_object(input: &str) -> Result<Vec<(String, &str)>, Error> {
+    let start = input.len() - input.trim_start().len();
+    let members = match json::json_object::<ParseFailure>(&input[start..]) {
+        Ok((rest, members)) if rest.trim().is_empty() => members,
+        Ok((rest, _)) => return Err(Error::at(input, input.len() - rest.len(), "unexpected content after the closing `}`")),
+        Err(nom::Err::Error(failure) | nom::Err::Failure(failure)) => return Err(Error::from_failure(input, failure)),
+        Err(nom::Err::Incomplete(_)) => return Err(Error::at(input, input.len(), "unexpected end of input")),
+    };
+    Ok(members
+        .into_iter()
+        .map(|(key, value)| (unescape_json_string(key).unwrap_or_default(), value))
+        .collect())
+}
+
+/// Recovering counterpart of [`parse_project`].
+pub fn parse_project_recovering(input: &str) -> Result<Recovered<Project<'_>>, Error> {
+    let Recovered { parsed, diagnostics } = parse_chunk_recovering(input)?;
+    if parsed.tag != "REAPER_PROJECT" {
+        let start = input.len() - input.trim_start().len();
+        return Err(Error::at(input, start, format!("expected a REAPER_PROJECT element, found {}", parsed.tag)));
+    }
+    Ok(Recovered {
+        parsed: Project(parsed),
+        diagnostics,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunk_error_has_position_and_path() {
+        let input = "<REAPER_PROJECT 0.1\n  <TRACK\n  >\n  <TRACK\n    <FXCHAIN\n      SHOW 0\n      }{ oops\n    >\n  >\n>\n";
+        let error = parse_chunk(input).unwrap_err();
+        assert_eq!((error.line, error.column), (7, 7));
+        assert_eq!(error.path, "REAPER_PROJECT > TRACK[1] > FXCHAIN[0]");
+        assert_eq!(
+            error.to_string(),
+            "7:7: expected an attribute, base64 data, a child element or `>` (in REAPER_PROJECT > TRACK[1] > FXCHAIN[0])"
+        );
+    }
+
+    #[test]
+    fn chunk_trailing_input() {
+        assert!(parse_chunk("<TRACK\n>\n\n").is_ok());
+
+        let error = parse_chunk("<TRACK\n>\n<TRACK\n>\n").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 1));
+        assert_eq!(error.reason, "unexpected content after the closing `>`");
+    }
+
+    #[test]
+    fn chunk_unexpected_end() {
+        let error = parse_chunk("<TRACK\n  NAME x\n").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 1));
+        assert_eq!(error.reason, "unexpected end of input, expected `>`");
+        assert_eq!(error.path, "TRACK");
+    }
+
+    #[test]
+    fn chunk_round_trip_keeps_whitespace_of_each_line() {
+        let input = "\n<A 1  \n  X 1\n    Y 2   \n\t<B\n\t  Z\n\t> \n   \n  00FF==\t\n >";
+        assert_eq!(parse_chunk(input).unwrap().to_string(), input);
+        assert_eq!(parse_chunk_recovering(input).unwrap().parsed.to_string(), input);
+
+        let mut element = parse_chunk(input).unwrap();
+        element.insert_attribute_at(1, "W", vec![]);
+        element.remove_attr("X");
+        assert_eq!(element.to_string(), "\n<A 1  \n  W\n    Y 2   \n\t<B\n\t  Z\n\t> \n   \n  00FF==\t\n >");
+    }
+
+    #[test]
+    fn project_requires_project_tag() {
+        assert!(parse_project(include_str!("../../StreamingPlugin.rpp")).is_ok());
+
+        let error = parse_project("  <TRACK\n>").err().unwrap();
+        assert_eq!((error.line, error.column), (1, 3));
+        assert_eq!(error.reason, "expected a REAPER_PROJECT element, found TRACK");
+    }
+}
+
 #[cfg(test)]
 mod integ_test {
     use std::fs;
@@ -36,7 +226,31 @@ mod integ_test {
         let input = include_str!("../../StreamingPlugin.rpp");
         let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
         let formatted = parsed.to_string();
-        let parsed = parse_element::<(_, ErrorKind)>(&formatted).unwrap().1;
+        let reparsed = parse_element::<(_, ErrorKind)>(&formatted).unwrap().1;
+        assert_eq!(parsed, reparsed);
+    }
+
+    #[test]
+    fn integ_test_lossless_round_trip() {
+        let input = include_str!("../../StreamingPlugin.rpp");
+        let parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+        assert_eq!(parsed.to_string(), input);
+    }
+
+    #[test]
+    fn integ_test_edit_keeps_other_lines() {
+        let input = include_str!("../../StreamingPlugin.rpp");
+        let mut parsed = parse_element::<(_, ErrorKind)>(input).unwrap().1;
+        parsed.set_attr("TEMPO", crate::RValues::ints([90, 4, 4]));
+        let formatted = parsed.to_string();
+
+        let changed: Vec<_> = input
+            .lines()
+            .zip(formatted.lines())
+            .filter(|(before, after)| before != after)
+            .collect();
+        assert_eq!(changed, vec![("  TEMPO 120 4 4", "  TEMPO 90 4 4")]);
+        assert_eq!(input.len() - 1, formatted.len());
     }
 
     #[test]
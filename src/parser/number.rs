@@ -1,12 +1,52 @@
 use crate::RValue;
-use nom::combinator::map;
-use nom::error::ParseError;
-use nom::number::complete::double;
+use nom::branch::alt;
+use nom::character::complete::{char, digit1};
+use nom::combinator::{eof, map_opt, map_res, opt, recognize};
+use nom::error::{FromExternalError, ParseError};
+use nom::number::complete::recognize_float;
+use nom::sequence::{pair, terminated};
 use nom::IResult;
 
+/// Parses a whole token as an integer if it is one, and as a float if it is made of digits with
+/// an optional sign, `.` and exponent. Words like `nan` or `inf` are left to be strings.
 pub fn parse_number<'a, E>(input: &'a str) -> IResult<&'a str, RValue<'a>, E>
 where
-    E: ParseError<&'a str>,
+    E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-    map(double, RValue::N)(input)
+    alt((
+        map_res(terminated(recognize(pair(opt(char('-')), digit1)), eof), |raw: &'a str| {
+            raw.parse().map(|i| RValue::I(i, Some(raw.into())))
+        }),
+        map_opt(terminated(recognize_float, eof), |raw: &'a str| {
+            raw.parse().ok().map(|f| RValue::F(f, Some(raw.into())))
+        }),
+    ))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use nom::error::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn integers_and_floats() {
+        assert_matches!(parse_number::<(_, ErrorKind)>("2863311530"), Ok(("", RValue::I(2863311530, _))));
+        assert_matches!(parse_number::<(_, ErrorKind)>("-1"), Ok(("", RValue::I(-1, _))));
+        assert_matches!(parse_number::<(_, ErrorKind)>("0.0025"), Ok(("", RValue::F(f, _))) if f == 0.0025);
+        assert_matches!(parse_number::<(_, ErrorKind)>("99999999999999999999"), Ok(("", RValue::F(..))));
+        assert_matches!(parse_number::<(_, ErrorKind)>("-1.5e3"), Ok(("", RValue::F(f, _))) if f == -1500.0);
+        assert!(parse_number::<(_, ErrorKind)>("1.5x").is_err());
+        for word in ["nan", "NaN", "inf", "-Inf", "infinity"] {
+            assert!(parse_number::<(_, ErrorKind)>(word).is_err(), "{word}");
+        }
+    }
+
+    #[test]
+    fn nan_and_inf_stay_strings() {
+        let element = crate::parse_chunk("<TRACK\n  NAME nan\n  <ITEM\n    NAME Inf\n  >\n>").unwrap();
+        let track = crate::Track(&element);
+        assert_eq!(track.name(), Some("nan"));
+        assert_eq!(track.items()[0].name(), Some("Inf"));
+    }
 }
@@ -1,33 +1,33 @@
 use nom::branch::alt;
-use nom::bytes::complete::{escaped_transform, is_not, tag};
-use nom::character::complete::{anychar, char};
-use nom::combinator::{map, not, value};
+use nom::bytes::complete::take_till;
+use nom::character::complete::char;
+use nom::combinator::map;
 use nom::error::ParseError;
 use nom::IResult;
 use nom::sequence::delimited;
 
 use crate::RValue;
 
+/// A string between two `quote` characters. REAPER does not escape anything inside quoted
+/// strings; it picks a delimiter the string does not contain instead.
+fn quoted_by<'a, E>(quote: char) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str, E>
+  where
+    E: ParseError<&'a str>,
+{
+  delimited(
+    char(quote),
+    take_till(move |c| c == quote || c == '\n' || c == '\r'),
+    char(quote),
+  )
+}
+
 pub fn parse_quoted_string<'a, E>(input: &'a str) -> IResult<&'a str, RValue<'a>, E>
   where
     E: ParseError<&'a str>,
 {
   map(
-    delimited(
-      char('"'),
-      escaped_transform(
-        is_not("\"\\"),
-        '\\',
-        alt((
-          value("\\", tag("\\")),
-          value("\"", tag("\"")),
-          value("\n", tag("n")),
-          value("\t", tag("t")),
-        )),
-      ),
-      char('"'),
-    ),
-    RValue::QS,
+    alt((quoted_by('"'), quoted_by('\''), quoted_by('`'))),
+    |s: &'a str| RValue::QS(s.into()),
   )(input)
 }
 
@@ -47,20 +47,40 @@ mod test {
   }
 
   #[test]
-  fn escaped_double_quote() {
-    let foo = parse_quoted_string::<(&'static str, ErrorKind)>(r#""this is a \"quoted\" string""#)
+  fn single_quoted_with_double_quotes() {
+    let foo = parse_quoted_string::<(&'static str, ErrorKind)>(r#"'this is a "quoted" string'"#)
       .unwrap()
       .1;
     assert_matches!(&foo, RValue::QS(qs) if qs == "this is a \"quoted\" string");
-    assert_matches!(foo.to_string().as_str(), r#""this is a \"quoted\" string""#);
+    assert_matches!(foo.to_string().as_str(), r#"'this is a "quoted" string'"#);
   }
 
   #[test]
-  fn escaped_tabs() {
-    let foo = parse_quoted_string::<(&'static str, ErrorKind)>(r#""this \\ is a \tquoted\t\n string""#)
+  fn backticks_with_both_quotes_and_backslash() {
+    let foo = parse_quoted_string::<(&'static str, ErrorKind)>(r#"`Mike's "Bass" \ DI`"#)
       .unwrap()
       .1;
-    assert_matches!(&foo, RValue::QS(qs) if qs == "this \\ is a \tquoted\t\n string");
-    assert_matches!(foo.to_string().as_str(), r#""this \\ is a \tquoted\t\n string""#);
+    assert_matches!(&foo, RValue::QS(qs) if qs == r#"Mike's "Bass" \ DI"#);
+    assert_matches!(foo.to_string().as_str(), r#"`Mike's "Bass" \ DI`"#);
+  }
+
+  #[test]
+  fn backslashes_are_literal() {
+    let foo = parse_quoted_string::<(&'static str, ErrorKind)>(r#""C:\Audio\" rest"#)
+      .unwrap();
+    assert_matches!(&foo.1, RValue::QS(qs) if qs == r#"C:\Audio\"#);
+    assert_eq!(foo.0, " rest");
+    assert_matches!(foo.1.to_string().as_str(), r#""C:\Audio\""#);
+  }
+
+  #[test]
+  fn all_delimiters_used() {
+    let foo = RValue::QS("it's `tick` \"quote\"".into());
+    assert_eq!(foo.to_string(), "`it's 'tick' \"quote\"`");
+  }
+
+  #[test]
+  fn unterminated() {
+    assert!(parse_quoted_string::<(&'static str, ErrorKind)>("\"no end\nNEXT").is_err());
   }
 }
@@ -0,0 +1,213 @@
+use nom::branch::alt;
+use nom::combinator::eof;
+use nom::error::ErrorKind;
+use nom::sequence::{preceded, tuple};
+use nom::{IResult, Offset};
+
+use crate::{Error, RElement, RFragment, RLayout, RLineLayout, RValue};
+use crate::parser::element::{consumed, parse_body_line, parse_header, trailing_whitespace, BodyKind, BodyLine, Header};
+use crate::parser::whitespace::{multispace_no_newline_0, whitespace_line_ending};
+
+type NomError<'a> = (&'a str, ErrorKind);
+
+/// A tree parsed in recovery mode, along with one diagnostic per problem that was skipped over.
+#[derive(Debug)]
+pub struct Recovered<T> {
+  pub parsed: T,
+  pub diagnostics: Vec<Error>,
+}
+
+/// Recursive-descent parser mirroring `parse_element`, except that lines it cannot parse are kept
+/// as [`RFragment::Raw`] and reported instead of failing the whole element.
+pub(crate) struct Recovery<'a> {
+  input: &'a str,
+  pub diagnostics: Vec<Error>,
+}
+
+impl<'a> Recovery<'a> {
+  pub fn new(input: &'a str) -> Self {
+    Self {
+      input,
+      diagnostics: vec![],
+    }
+  }
+
+  fn report(&mut self, at: &'a str, reason: &str) {
+    self.diagnostics.push(Error::at(self.input, self.input.offset(at), reason));
+  }
+
+  /// The end of a line, or of the input.
+  fn line_end(i: &'a str) -> IResult<&'a str, &'a str, NomError<'a>> {
+    alt((whitespace_line_ending, preceded(multispace_no_newline_0, eof)))(i)
+  }
+
+  /// `<TAG args` up to and including the line ending.
+  fn header(i: &'a str) -> IResult<&'a str, (Header<'a>, &'a str), NomError<'a>> {
+    tuple((parse_header, Self::line_end))(i)
+  }
+
+  /// Parses an element starting at `<`. Returns `None` if the line is not an element header.
+  pub fn element(&mut self, i: &'a str) -> Option<(&'a str, RElement<'a>)> {
+    let (rest, ((tag, args), line_ending)) = Self::header(i).ok()?;
+    Some(self.element_rest(i, rest, None, tag, args, line_ending))
+  }
+
+  /// The body and closing `>` of an element whose header line, starting at `line`, has been read.
+  fn element_rest(
+    &mut self,
+    line: &'a str,
+    mut i: &'a str,
+    parent: Option<&str>,
+    tag: &'a str,
+    args: Vec<RValue<'a>>,
+    line_ending: &'a str,
+  ) -> (&'a str, RElement<'a>) {
+    let kind = BodyKind::of(parent, tag, &args);
+    let mut element = RElement::new(tag, args);
+    let mut layout = RLayout {
+      header_trailing: trailing_whitespace(consumed(line, i)).into(),
+      line_ending: line_ending.into(),
+      ..RLayout::default()
+    };
+
+    loop {
+      let (rest, indent) = multispace_no_newline_0::<_, NomError>(i).unwrap_or((i, ""));
+
+      if rest.is_empty() {
+        self.report(rest, "unexpected end of input, expected `>`");
+        i = rest;
+        break;
+      }
+
+      let line = parse_body_line::<NomError>(kind)(i).map(|(after, (_, line))| (after, line));
+      match line {
+        Ok((after, BodyLine::Close)) => {
+          layout.close_indent = indent.into();
+          i = after;
+          break;
+        }
+        Ok((after, BodyLine::Open(tag, args))) => {
+          let Ok((after, line_ending)) = Self::line_end(after) else {
+            layout.body_indent.get_or_insert(indent.into());
+            i = self.raw_line(&mut element, &mut layout, indent, rest);
+            continue;
+          };
+          let (after, mut child) = self.element_rest(rest, after, Some(element.tag.as_ref()), tag, args, line_ending);
+          layout.body_indent.get_or_insert(indent.into());
+          if let Some(child_layout) = child.layout.as_mut() {
+            child_layout.indent = indent.into();
+          }
+          element.content.push(RFragment::Child(child));
+          match Self::line_end(after) {
+            Ok((next, _)) => {
+              layout.lines.push(Some(line_layout(indent, trailing_whitespace(consumed(after, next)))));
+              i = next;
+            }
+            Err(_) => {
+              layout.lines.push(Some(line_layout(indent, "")));
+              i = self.raw_line(&mut element, &mut layout, "", after);
+            }
+          }
+        }
+        Ok((after, BodyLine::Fragment(fragment))) => {
+          if !matches!(fragment, RFragment::Empty) {
+            layout.body_indent.get_or_insert(indent.into());
+          }
+          element.content.push(fragment);
+          layout.lines.push(Some(line_layout(indent, trailing_whitespace(consumed(rest, after)))));
+          i = after;
+        }
+        Err(_) => {
+          layout.body_indent.get_or_insert(indent.into());
+          i = self.raw_line(&mut element, &mut layout, indent, rest);
+        }
+      }
+    }
+
+    element.layout = Some(layout);
+    (i, element)
+  }
+
+  /// Keeps everything up to the end of the line, or up to a `<TAG` or `>` that starts a structure
+  /// token later on the line, as a raw fragment. Returns the input to resume at.
+  fn raw_line(&mut self, element: &mut RElement<'a>, layout: &mut RLayout<'a>, indent: &'a str, i: &'a str) -> &'a str {
+    let line_len = i.find(['\r', '\n']).unwrap_or(i.len());
+    let resync = i[..line_len]
+      .char_indices()
+      .skip(1)
+      .find(|(pos, c)| match c {
+        '<' => Self::header(&i[*pos..]).is_ok(),
+        '>' => i[pos + 1..line_len].trim().is_empty(),
+        _ => false,
+      })
+      .map(|(pos, _)| pos);
+
+    let raw_len = resync.unwrap_or(line_len);
+    let raw = i[..raw_len].trim_end();
+    self.report(i, &format!("unrecognized line `{raw}`"));
+    element.content.push(RFragment::Raw(raw.into()));
+    let trailing = if resync.is_some() { "" } else { &i[raw.len()..raw_len] };
+    layout.lines.push(Some(line_layout(indent, trailing)));
+
+    match resync {
+      Some(_) => &i[raw_len..],
+      None => whitespace_line_ending::<NomError>(&i[line_len..]).map_or(&i[line_len..], |(after, _)| after),
+    }
+  }
+}
+
+fn line_layout<'a>(indent: &'a str, trailing: &'a str) -> RLineLayout<'a> {
+  RLineLayout {
+    indent: indent.into(),
+    trailing: trailing.into(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::parser::{parse_chunk_recovering, parse_project_recovering};
+
+  use super::*;
+
+  #[test]
+  fn bad_line_is_kept_raw() {
+    let input = "<TRACK\n  NAME bass\n  -1 }{ oops\n  NCHAN 2\n>\n";
+    let recovered = parse_chunk_recovering(input).unwrap();
+
+    assert_eq!(recovered.parsed.get_num_attr("NCHAN", 0), Some(2.0));
+    assert_matches!(&recovered.parsed.content[1], RFragment::Raw(raw) if raw == "-1 }{ oops");
+    assert_eq!(recovered.diagnostics.len(), 1);
+    assert_eq!((recovered.diagnostics[0].line, recovered.diagnostics[0].column), (3, 3));
+    assert_eq!(recovered.diagnostics[0].path, "TRACK");
+    assert_eq!(recovered.parsed.to_string(), input);
+  }
+
+  #[test]
+  fn resync_at_structure_tokens() {
+    let input = "<TRACK\n  -- <ITEM\n    LENGTH 1\n  >\n  ## >\n";
+    let recovered = parse_chunk_recovering(input).unwrap();
+
+    assert_eq!(recovered.diagnostics.len(), 2);
+    assert_eq!(recovered.parsed.children_with_tag("ITEM").count(), 1);
+    assert_matches!(&recovered.parsed.content[..], [RFragment::Raw(a), RFragment::Child(_), RFragment::Raw(b)] if a == "--" && b == "##");
+  }
+
+  #[test]
+  fn unclosed_elements_at_end_of_input() {
+    let input = "<REAPER_PROJECT 0.1\n  <TRACK\n    NAME x\n";
+    let recovered = parse_project_recovering(input).unwrap();
+
+    assert_eq!(recovered.parsed.tracks().len(), 1);
+    assert_eq!(recovered.diagnostics.len(), 2);
+    assert!(recovered.diagnostics.iter().all(|d| d.reason == "unexpected end of input, expected `>`"));
+  }
+
+  #[test]
+  fn valid_project_has_no_diagnostics() {
+    let input = include_str!("../../StreamingPlugin.rpp");
+    let recovered = parse_chunk_recovering(input).unwrap();
+
+    assert!(recovered.diagnostics.is_empty());
+    assert_eq!(recovered.parsed.to_string(), input);
+  }
+}
@@ -0,0 +1,114 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_till1};
+use nom::character::complete::{char, digit1};
+use nom::combinator::{cut, eof, map, map_res, opt, value};
+use nom::error::{FromExternalError, ParseError};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded};
+use nom::IResult;
+
+use crate::select::{Op, Predicate, Selector, Step};
+use crate::RValue;
+
+use super::identifier::parse_identifier;
+use super::quoted_string::parse_quoted_string;
+
+fn parse_literal<'a, E>(input: &'a str) -> IResult<&'a str, String, E>
+  where
+    E: ParseError<&'a str>,
+{
+  alt((
+    map(parse_quoted_string, |value| match value {
+      RValue::QS(s) => s.into_owned(),
+      _ => unreachable!(),
+    }),
+    map(take_till1(|c| c == ']'), str::to_string),
+  ))(input)
+}
+
+fn parse_op<'a, E>(input: &'a str) -> IResult<&'a str, Op, E>
+  where
+    E: ParseError<&'a str>,
+{
+  alt((value(Op::Contains, tag("~=")), value(Op::Ne, tag("!=")), value(Op::Eq, tag("="))))(input)
+}
+
+fn parse_predicate<'a, E>(input: &'a str) -> IResult<&'a str, Predicate, E>
+  where
+    E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
+{
+  delimited(
+    char('['),
+    cut(alt((
+      map_res(digit1, |n: &str| n.parse().map(Predicate::Index)),
+      map(
+        pair(preceded(opt(char('@')), parse_identifier), opt(pair(parse_op, parse_literal))),
+        |(name, comparison)| match comparison {
+          Some((op, literal)) => Predicate::Attr(name.to_string(), op, literal),
+          None => Predicate::HasAttr(name.to_string()),
+        },
+      ),
+    ))),
+    cut(char(']')),
+  )(input)
+}
+
+fn parse_step<'a, E>(descendant: bool) -> impl FnMut(&'a str) -> IResult<&'a str, Step, E>
+  where
+    E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
+{
+  map(
+    pair(
+      alt((map(parse_identifier, |t: &str| Some(t.to_string())), value(None, char('*')))),
+      many0(parse_predicate),
+    ),
+    move |(tag, predicates)| Step {
+      descendant,
+      tag,
+      predicates,
+    },
+  )
+}
+
+fn parse_axis<'a, E>(input: &'a str) -> IResult<&'a str, bool, E>
+  where
+    E: ParseError<&'a str>,
+{
+  alt((value(true, tag("//")), value(false, tag("/"))))(input)
+}
+
+pub(crate) fn parse_selector_body<'a, E>(input: &'a str) -> IResult<&'a str, Selector, E>
+  where
+    E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
+{
+  let (mut input, descendant) = map(opt(tag("//")), |t: Option<&str>| t.is_some())(input)?;
+  let (rest, first) = cut(parse_step(descendant))(input)?;
+  input = rest;
+
+  let mut steps = vec![first];
+  let mut attribute = None;
+  loop {
+    match preceded(tag("/@"), cut(parse_identifier))(input) {
+      Ok((rest, name)) => {
+        attribute = Some(name.to_string());
+        input = rest;
+        break;
+      }
+      Err(nom::Err::Error(_)) => {}
+      Err(e) => return Err(e),
+    }
+
+    match parse_axis(input) {
+      Ok((rest, descendant)) => {
+        let (rest, step) = cut(parse_step(descendant))(rest)?;
+        steps.push(step);
+        input = rest;
+      }
+      Err(nom::Err::Error(_)) => break,
+      Err(e) => return Err(e),
+    }
+  }
+
+  let (input, _) = cut(eof)(input)?;
+  Ok((input, Selector { steps, attribute }))
+}
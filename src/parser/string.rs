@@ -1,20 +1,15 @@
-use nom::{AsChar, InputTakeAtPosition, IResult};
-use nom::bytes::complete::{take_till1, take_until};
-use nom::character::complete::{anychar, char, multispace1};
-use nom::character::streaming::space1;
+use nom::IResult;
+use nom::bytes::complete::take_till1;
 use nom::combinator::map;
 use nom::error::{FromExternalError, ParseError};
-use nom::sequence::terminated;
 
 use crate::RValue;
 
-use super::whitespace::multispace_no_newline_1;
-
 pub fn parse_string<'a, E>(input: &'a str) -> IResult<&'a str, RValue<'a>, E>
   where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
-  map(take_till1(char::is_whitespace), RValue::S)(input)
+  map(take_till1(char::is_whitespace), |s: &'a str| RValue::S(s.into()))(input)
 }
 
 #[cfg(test)]
@@ -27,14 +22,14 @@ mod test {
 
   #[test]
   fn test_string_simple() {
-    assert_matches!(parse_string::<(_, ErrorKind)>("hello world"), Ok((" world", RValue::S("hello"))));
+    assert_matches!(parse_string::<(_, ErrorKind)>("hello world"), Ok((" world", RValue::S(s))) if s == "hello");
   }
 
   #[test]
   fn test_string_dotted() {
     assert_matches!(
             parse_string::<(_, ErrorKind)>("StreamingPlugin.vst"),
-            Ok(("", RValue::S("StreamingPlugin.vst")))
+            Ok(("", RValue::S(s))) if s == "StreamingPlugin.vst"
         );
   }
 
@@ -42,7 +37,7 @@ mod test {
   fn test_string_angled() {
     assert_matches!(
             parse_string::<(_, ErrorKind)>("54811357<56535403445ADD73747265616D696E67>"),
-            Ok(("", RValue::S("54811357<56535403445ADD73747265616D696E67>")))
+            Ok(("", RValue::S(s))) if s == "54811357<56535403445ADD73747265616D696E67>"
         );
   }
 }
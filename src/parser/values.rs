@@ -1,16 +1,14 @@
+use std::borrow::Cow;
+
 use nom::branch::alt;
-use nom::character::complete::{char, multispace1, none_of};
 use nom::combinator::map;
 use nom::error::{FromExternalError, ParseError};
-use nom::multi::{fold_many0, separated_list0};
-use nom::number::complete::double;
-use nom::sequence::{delimited, preceded, terminated};
-use nom::{AsChar, IResult, InputTakeAtPosition};
+use nom::multi::separated_list0;
+use nom::IResult;
 
-use crate::parser::number::parse_number;
 use crate::parser::whitespace::multispace_no_newline_1;
 use crate::parser::{number, quoted_string, string};
-use crate::RValue;
+use crate::{Guid, RValue};
 
 fn parse_value<'a, E>(input: &'a str) -> IResult<&'a str, RValue<'a>, E>
 where
@@ -19,9 +17,12 @@ where
     alt((
         quoted_string::parse_quoted_string,
         map(string::parse_string, |s| match s {
-            RValue::S(str_content) => match number::parse_number::<E>(str_content) {
+            RValue::S(Cow::Borrowed(str_content)) => match number::parse_number::<E>(str_content) {
                 Ok(("", success_number)) => success_number,
-                _ => RValue::S(str_content),
+                _ => match str_content.parse::<Guid>() {
+                    Ok(guid) => RValue::G(guid),
+                    Err(_) => RValue::S(Cow::Borrowed(str_content)),
+                },
             },
             non_str_val => non_str_val,
         }),
@@ -45,5 +46,6 @@ mod test {
     fn test_vst_params() {
         let input = "\"VST: Streaming Plugin (Distopik)\" StreamingPlugin.vst 0 \"\" 54811357<56535403445ADD73747265616D696E67> \"\"\n";
         let parsed = parse_value_list::<(_, ErrorKind)>(input).unwrap().1;
+        assert_eq!(parsed.len(), 6);
     }
 }
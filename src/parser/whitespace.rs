@@ -1,6 +1,6 @@
-use nom::character::complete::{char, line_ending, newline};
+use nom::character::complete::line_ending;
 use nom::error::{ErrorKind, ParseError};
-use nom::sequence::{terminated, tuple};
+use nom::sequence::preceded;
 use nom::{AsChar, IResult, InputTakeAtPosition};
 
 pub fn multispace_no_newline_0<T, E: ParseError<T>>(input: T) -> IResult<T, T, E>
@@ -14,11 +14,12 @@ where
     })
 }
 
+/// Trailing whitespace and the line ending, returning the line ending itself (`\n` or `\r\n`).
 pub fn whitespace_line_ending<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
 where
     E: ParseError<&'a str>,
 {
-    terminated(multispace_no_newline_0, line_ending)(input)
+    preceded(multispace_no_newline_0, line_ending)(input)
 }
 
 pub fn multispace_no_newline_1<T, E: ParseError<T>>(input: T) -> IResult<T, T, E>
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::parser::{parse_body_line, parse_chunk, BodyKind, BodyLine};
+use crate::{Error, RElement, RFragment};
+
+/// Reads an RPP project from any [`BufRead`] one top-level fragment at a time, so that only the
+/// largest single child (e.g. one track with its items and plugin states) has to fit in memory.
+///
+/// The `<REAPER_PROJECT ...` line is read by [`ProjectReader::new`] and available from
+/// [`ProjectReader::header`]; iterating yields the project's attributes and child elements as
+/// owned fragments, and ends at the project's closing `>`.
+pub struct ProjectReader<R> {
+  reader: R,
+  header: RElement<'static>,
+  kind: BodyKind,
+  line: usize,
+  siblings: HashMap<String, usize>,
+  done: bool,
+}
+
+impl<R: Read> ProjectReader<BufReader<R>> {
+  pub fn from_read(reader: R) -> Result<Self, Error> {
+    Self::new(BufReader::new(reader))
+  }
+}
+
+impl<R: BufRead> ProjectReader<R> {
+  pub fn new(reader: R) -> Result<Self, Error> {
+    let mut rv = Self {
+      reader,
+      header: RElement::default(),
+      kind: BodyKind::Attributes,
+      line: 0,
+      siblings: HashMap::new(),
+      done: false,
+    };
+
+    let mut first = String::new();
+    while first.trim().is_empty() {
+      first.clear();
+      if rv.read_line(&mut first)? == 0 {
+        return Err(rv.error(1, "expected a REAPER_PROJECT element, found end of input"));
+      }
+    }
+
+    // An opening line followed by `>` is a complete element without a body.
+    let header = format!("{}>", first.trim_start());
+    let header = parse_chunk(&header).map_err(|e| rv.relocate(e, rv.line, None))?;
+    if header.tag != "REAPER_PROJECT" {
+      return Err(rv.error(1, format!("expected a REAPER_PROJECT element, found {}", header.tag)));
+    }
+    rv.kind = BodyKind::of(None, &header.tag, &header.args);
+    rv.header = header.into_owned();
+    Ok(rv)
+  }
+
+  /// The project element with its arguments, without any content.
+  pub fn header(&self) -> &RElement<'static> {
+    &self.header
+  }
+
+  fn read_line(&mut self, buf: &mut String) -> Result<usize, Error> {
+    let read = self.reader.read_line(buf).map_err(|e| self.error(1, e.to_string()))?;
+    if read > 0 {
+      self.line += 1;
+    }
+    Ok(read)
+  }
+
+  fn error(&self, column: usize, reason: impl Into<String>) -> Error {
+    Error {
+      line: self.line.max(1),
+      column,
+      path: self.header.tag.to_string(),
+      reason: reason.into(),
+    }
+  }
+
+  /// Turns an error from parsing a chunk that started at `first_line` into one relative to the
+  /// whole project.
+  fn relocate(&self, error: Error, first_line: usize, child: Option<&str>) -> Error {
+    let root = &self.header.tag;
+    let path = match (child, error.path.split_once(" > ")) {
+      (None, _) => root.to_string(),
+      (Some(child), Some((_, rest))) => format!("{root} > {child} > {rest}"),
+      (Some(child), None) => format!("{root} > {child}"),
+    };
+    Error {
+      line: error.line + first_line - 1,
+      path,
+      ..error
+    }
+  }
+
+  /// Reads lines up to and including the `>` that closes the `tag` element opened on `first`,
+  /// whose body is of the given kind.
+  fn read_child(&mut self, first: String, tag: String, kind: BodyKind) -> Result<RFragment<'static>, Error> {
+    let first_line = self.line;
+    let indent_len = first.len() - first.trim_start().len();
+    let mut chunk = first;
+    let mut open = vec![(tag.clone(), kind)];
+    while let Some((parent, kind)) = open.last() {
+      let start = chunk.len();
+      if self.read_line(&mut chunk)? == 0 {
+        return Err(self.error(1, "unexpected end of input, expected `>`"));
+      }
+      // Lines that do not parse are reported by `parse_chunk` below, with their position.
+      match parse_body_line(&chunk[start..], *kind) {
+        Ok(BodyLine::Open(tag, args)) => {
+          let kind = BodyKind::of(Some(parent), tag, &args);
+          open.push((tag.to_string(), kind));
+        }
+        Ok(BodyLine::Close) => {
+          open.pop();
+        }
+        _ => {}
+      }
+    }
+
+    let index = self.siblings.entry(tag.clone()).or_default();
+    let child_path = format!("{tag}[{index}]");
+    *index += 1;
+
+    let mut element = parse_chunk(&chunk)
+      .map_err(|e| self.relocate(e, first_line, Some(&child_path)))?
+      .into_owned();
+    if let Some(layout) = element.layout.as_mut() {
+      layout.indent = chunk[..indent_len].to_string().into();
+    }
+    Ok(RFragment::Child(element))
+  }
+
+  fn read_fragment(&mut self) -> Result<Option<RFragment<'static>>, Error> {
+    let mut line = String::new();
+    if self.read_line(&mut line)? == 0 {
+      return Err(self.error(1, "unexpected end of input, expected `>`"));
+    }
+
+    if !line.ends_with('\n') {
+      line.push('\n');
+    }
+    let (tag, kind) = match parse_body_line(&line, self.kind).map_err(|e| self.relocate(e, self.line, None))? {
+      BodyLine::Close => {
+        self.done = true;
+        return Ok(None);
+      }
+      BodyLine::Fragment(fragment) => return Ok(Some(fragment.into_owned())),
+      BodyLine::Open(tag, args) => (tag.to_string(), BodyKind::of(Some(&self.header.tag), tag, &args)),
+    };
+    self.read_child(line, tag, kind).map(Some)
+  }
+}
+
+impl<R: BufRead> Iterator for ProjectReader<R> {
+  type Item = Result<RFragment<'static>, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let rv = self.read_fragment();
+    if rv.is_err() {
+      self.done = true;
+    }
+    rv.transpose()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::io::Cursor;
+
+  use super::*;
+
+  const INPUT: &str = include_str!("../StreamingPlugin.rpp");
+
+  #[test]
+  fn reads_children_one_at_a_time() {
+    let reader = ProjectReader::from_read(Cursor::new(INPUT)).unwrap();
+    assert_eq!(reader.header().get_str_arg(1), Some("6.43/macOS-arm64"));
+
+    let fragments: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    let tracks: Vec<_> = fragments
+      .iter()
+      .filter_map(|frag| match frag {
+        RFragment::Child(c) if c.tag == "TRACK" => Some(c),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[1].get_str_attr("NAME", 0), Some("UREI_Bass"));
+    assert_matches!(&fragments[0], RFragment::Attribute(name, _) if name == "RIPPLE");
+
+    let start = INPUT.find("  <TRACK {F5E7B582").unwrap();
+    let end = INPUT.rfind(">\r\n").unwrap();
+    assert_eq!(tracks[1].to_string(), &INPUT[start..end]);
+  }
+
+  #[test]
+  fn errors_are_relative_to_the_project() {
+    let input = "<REAPER_PROJECT 0.1\n  TEMPO 120 4 4\n  <TRACK\n  >\n  <TRACK\n    <FXCHAIN\n      }{\n    >\n  >\n>\n";
+    let results: Vec<_> = ProjectReader::from_read(Cursor::new(input)).unwrap().collect();
+    assert_eq!(results.len(), 3);
+    let error = results[2].as_ref().unwrap_err();
+    assert_eq!((error.line, error.column), (7, 7));
+    assert_eq!(error.path, "REAPER_PROJECT > TRACK[1] > FXCHAIN[0]");
+  }
+
+  #[test]
+  fn rejects_other_elements_and_truncation() {
+    assert!(ProjectReader::from_read(Cursor::new("<TRACK\n>\n")).is_err());
+
+    let results: Vec<_> = ProjectReader::from_read(Cursor::new("<REAPER_PROJECT\n  <TRACK\n")).unwrap().collect();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_ref().unwrap_err().reason, "unexpected end of input, expected `>`");
+  }
+}
@@ -0,0 +1,312 @@
+use crate::reaper::vst::StateReader;
+use crate::reaper::{amplitude_to_db, db_to_amplitude, PluginStateError, VstPlugin};
+
+/// Unique ID of ReaEQ, `reeq`.
+pub const REAEQ_ID: u32 = u32::from_be_bytes(*b"reeq");
+/// Unique ID of ReaComp, `recm`.
+pub const REACOMP_ID: u32 = u32::from_be_bytes(*b"recm");
+
+fn check_plugin(plugin: &VstPlugin, id: u32) -> Result<(), PluginStateError> {
+    if plugin.id.unique_id == id {
+        Ok(())
+    } else {
+        Err(PluginStateError::UnexpectedPlugin(plugin.name.clone()))
+    }
+}
+
+code_enum! {
+    /// Filter shape of a ReaEQ band. The legacy band type 2 is kept as `Other(2)`.
+    pub enum EqBandType: u32 {
+        LowShelf = 0,
+        HighShelf = 1,
+        LowPass = 3,
+        HighPass = 4,
+        AllPass = 5,
+        Notch = 6,
+        BandPass = 7,
+        Band = 8,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqBand {
+    pub band_type: EqBandType,
+    pub enabled: bool,
+    /// Center or corner frequency in Hz.
+    pub frequency: f64,
+    /// Linear gain, in dB from [`EqBand::gain_db`].
+    pub gain: f64,
+    /// Bandwidth in octaves, which is what ReaEQ stores rather than Q.
+    pub bandwidth: f64,
+}
+
+impl EqBand {
+    pub fn gain_db(&self) -> f64 {
+        amplitude_to_db(self.gain)
+    }
+
+    pub fn set_gain_db(&mut self, db: f64) {
+        self.gain = db_to_amplitude(db);
+    }
+
+    /// The Q equivalent to the band's bandwidth.
+    pub fn q(&self) -> f64 {
+        let ratio = 2f64.powf(self.bandwidth);
+        ratio.sqrt() / (ratio - 1.0)
+    }
+
+    pub fn set_q(&mut self, q: f64) {
+        let x = (1.0 + 1.0 / (2.0 * q * q)) + ((1.0 + 1.0 / (2.0 * q * q)).powi(2) - 1.0).sqrt();
+        self.bandwidth = x.log2();
+    }
+}
+
+/// ReaEQ's chunk: a version word and the band count, followed by each band's type, enabled flag,
+/// frequency, gain and bandwidth. Whatever follows the bands is kept as-is, so that an unchanged
+/// chunk encodes to the same bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReaEq {
+    pub bands: Vec<EqBand>,
+    version: u32,
+    tail: Vec<u8>,
+}
+
+impl ReaEq {
+    pub fn decode(chunk: &[u8]) -> Result<Self, PluginStateError> {
+        let mut reader = StateReader::new(chunk);
+        reader.section("ReaEQ header");
+        let version = reader.u32()?;
+        let count = reader.u32()?;
+        reader.section("ReaEQ bands");
+        let bands = (0..count)
+            .map(|_| {
+                Ok(EqBand {
+                    band_type: EqBandType::from_code(reader.u32()?),
+                    enabled: reader.u32()? != 0,
+                    frequency: reader.f64()?,
+                    gain: reader.f64()?,
+                    bandwidth: reader.f64()?,
+                })
+            })
+            .collect::<Result<_, PluginStateError>>()?;
+        Ok(ReaEq {
+            bands,
+            version,
+            tail: reader.rest().to_vec(),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut rv = vec![];
+        rv.extend(self.version.to_le_bytes());
+        rv.extend((self.bands.len() as u32).to_le_bytes());
+        for band in &self.bands {
+            rv.extend(band.band_type.code().to_le_bytes());
+            rv.extend((band.enabled as u32).to_le_bytes());
+            rv.extend(band.frequency.to_le_bytes());
+            rv.extend(band.gain.to_le_bytes());
+            rv.extend(band.bandwidth.to_le_bytes());
+        }
+        rv.extend(&self.tail);
+        rv
+    }
+
+    pub fn from_plugin(plugin: &VstPlugin) -> Result<Self, PluginStateError> {
+        check_plugin(plugin, REAEQ_ID)?;
+        Self::decode(&plugin.chunk)
+    }
+
+    /// Replaces the chunk of `plugin`; write the plugin back with [`VstPlugin::write_to`].
+    pub fn write_to(&self, plugin: &mut VstPlugin) {
+        plugin.chunk = self.encode();
+    }
+}
+
+code_enum! {
+    /// What ReaComp's detector listens to.
+    pub enum DetectorInput: u32 {
+        Main = 0,
+        /// Channels 3/4, fed by a sidechain send.
+        Auxiliary = 1,
+        MainLeft = 2,
+        MainRight = 3,
+        AuxiliaryLeft = 4,
+        AuxiliaryRight = 5,
+    }
+}
+
+impl DetectorInput {
+    /// Whether the detector listens to a sidechain rather than the track itself.
+    pub fn is_sidechain(self) -> bool {
+        matches!(self, DetectorInput::Auxiliary | DetectorInput::AuxiliaryLeft | DetectorInput::AuxiliaryRight)
+    }
+}
+
+/// ReaComp's chunk: threshold, ratio, attack, release and knee as doubles, then the detector
+/// input. The remaining parameters are kept as-is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReaComp {
+    /// Linear threshold, in dB from [`ReaComp::threshold_db`].
+    pub threshold: f64,
+    /// Compression ratio, `n` for `n:1`.
+    pub ratio: f64,
+    /// Attack time in milliseconds.
+    pub attack: f64,
+    /// Release time in milliseconds.
+    pub release: f64,
+    /// Knee size in dB.
+    pub knee: f64,
+    pub detector_input: DetectorInput,
+    tail: Vec<u8>,
+}
+
+impl ReaComp {
+    pub fn decode(chunk: &[u8]) -> Result<Self, PluginStateError> {
+        let mut reader = StateReader::new(chunk);
+        reader.section("ReaComp parameters");
+        Ok(ReaComp {
+            threshold: reader.f64()?,
+            ratio: reader.f64()?,
+            attack: reader.f64()?,
+            release: reader.f64()?,
+            knee: reader.f64()?,
+            detector_input: DetectorInput::from_code(reader.u32()?),
+            tail: reader.rest().to_vec(),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut rv = vec![];
+        for v in [self.threshold, self.ratio, self.attack, self.release, self.knee] {
+            rv.extend(v.to_le_bytes());
+        }
+        rv.extend(self.detector_input.code().to_le_bytes());
+        rv.extend(&self.tail);
+        rv
+    }
+
+    pub fn threshold_db(&self) -> f64 {
+        amplitude_to_db(self.threshold)
+    }
+
+    pub fn set_threshold_db(&mut self, db: f64) {
+        self.threshold = db_to_amplitude(db);
+    }
+
+    pub fn from_plugin(plugin: &VstPlugin) -> Result<Self, PluginStateError> {
+        check_plugin(plugin, REACOMP_ID)?;
+        Self::decode(&plugin.chunk)
+    }
+
+    /// Like [`ReaEq::write_to`].
+    pub fn write_to(&self, plugin: &mut VstPlugin) {
+        plugin.chunk = self.encode();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_float_eq::*;
+
+    use crate::parser::parse_chunk;
+    use crate::RElement;
+
+    use super::*;
+
+    /// ReaEQ with a low shelf at 100 Hz, -6 dB, a band at 1 kHz, +6 dB, a disabled high pass at
+    /// 40 Hz and a legacy type 2 band at 8 kHz. Built by hand with the layout `ReaEq::decode`
+    /// reads, not saved by REAPER, so it cannot catch a wrong layout.
+    const REAEQ: &str = "<VST \"VST: ReaEQ (Cockos)\" reaeq.vst.dylib 0 \"\" 1919247729<56535472656571726561657100000000> \"\"
+  cWVlcu5e7f4CAAAAAQAAAAAAAAACAAAAAAAAAAIAAAABAAAAAAAAAAIAAAAAAAAAlAAAAAEAAAAAABAA
+  IQAAAAQAAAAAAAAAAQAAAAAAAAAAAFlAAAAAAAAA4D8AAAAAAAAAQAgAAAABAAAAAAAAAABAj0AAAAAAAAAAQAAAAAAAAPA/BAAAAAAAAAAAAAAAAABEQAAAAAAAAPA/
+  AAAAAAAA8D8CAAAAAQAAAAAAAAAAQL9AAAAAAAAA8D8AAAAAAADgPwEAAAAAAAAAAADwPw==
+  AAAQAAAA
+>
+";
+
+    /// ReaComp at -12 dB, 4:1, 3 ms attack, 100 ms release and no knee, detecting on the
+    /// auxiliary inputs. Built by hand like [`REAEQ`].
+    const REACOMP: &str = "<VST \"VST: ReaComp (Cockos)\" reacomp.vst.dylib 0 \"\" 1919247213<5653547265636D72656163636F6D7000> \"\"
+  bWNlcu5e7f4CAAAAAQAAAAAAAAACAAAAAAAAAAIAAAABAAAAAAAAAAIAAAAAAAAAOAAAAAEAAAAAABAA
+  AAAAAAAA0D8AAAAAAAAQQAAAAAAAAAhAAAAAAAAAWUAAAAAAAAAAAAEAAAAAAAAAAAAAAAAAAAA=
+  AAAQAAAA
+>
+";
+
+    fn load(input: &str) -> (RElement<'_>, VstPlugin) {
+        let element = parse_chunk(input).unwrap();
+        let plugin = VstPlugin::from_element(&element).unwrap();
+        (element, plugin)
+    }
+
+    #[test]
+    fn reaeq_bands() {
+        let (_, plugin) = load(REAEQ);
+        let eq = ReaEq::from_plugin(&plugin).unwrap();
+        assert_eq!(eq.encode(), plugin.chunk);
+
+        let types: Vec<_> = eq.bands.iter().map(|band| (band.band_type, band.enabled, band.frequency)).collect();
+        assert_eq!(
+            types,
+            vec![
+                (EqBandType::LowShelf, true, 100.0),
+                (EqBandType::Band, true, 1000.0),
+                (EqBandType::HighPass, false, 40.0),
+                (EqBandType::Other(2), true, 8000.0),
+            ]
+        );
+        assert_float_absolute_eq!(eq.bands[0].gain_db(), -6.0206, 0.0001);
+        assert_float_absolute_eq!(eq.bands[0].q(), 2.0 / 3.0, 1e-9);
+        assert_float_absolute_eq!(eq.bands[1].gain_db(), 6.0206, 0.0001);
+        assert_float_absolute_eq!(eq.bands[1].q(), std::f64::consts::SQRT_2, 1e-9);
+        assert_float_absolute_eq!(eq.bands[3].bandwidth, 0.5, 1e-9);
+
+        assert_eq!(ReaEq::decode(&plugin.chunk[..20]), Err(PluginStateError::Truncated("ReaEQ bands")));
+        assert_eq!(ReaComp::from_plugin(&plugin), Err(PluginStateError::UnexpectedPlugin("VST: ReaEQ (Cockos)".into())));
+    }
+
+    #[test]
+    fn reaeq_edit() {
+        let (mut element, mut plugin) = load(REAEQ);
+        let mut eq = ReaEq::from_plugin(&plugin).unwrap();
+        eq.bands[1].set_gain_db(3.0);
+        eq.bands[1].set_q(0.7);
+        eq.bands.remove(3);
+        eq.write_to(&mut plugin);
+        plugin.write_to(&mut element);
+
+        let reread = ReaEq::from_plugin(&VstPlugin::from_element(&element).unwrap()).unwrap();
+        assert_eq!(reread.bands.len(), 3);
+        assert_float_absolute_eq!(reread.bands[1].gain_db(), 3.0, 1e-9);
+        assert_float_absolute_eq!(reread.bands[1].q(), 0.7, 1e-9);
+        assert_eq!(reread.tail, eq.tail);
+    }
+
+    #[test]
+    fn reacomp_parameters() {
+        let (_, plugin) = load(REACOMP);
+        let comp = ReaComp::from_plugin(&plugin).unwrap();
+        assert_eq!(comp.encode(), plugin.chunk);
+
+        assert_float_absolute_eq!(comp.threshold_db(), -12.0412, 0.0001);
+        assert_eq!((comp.ratio, comp.attack, comp.release, comp.knee), (4.0, 3.0, 100.0, 0.0));
+        assert_eq!(comp.detector_input, DetectorInput::Auxiliary);
+        assert!(comp.detector_input.is_sidechain());
+        assert_eq!(comp.tail.len(), 12);
+    }
+
+    #[test]
+    fn reacomp_edit() {
+        let (mut element, mut plugin) = load(REACOMP);
+        let mut comp = ReaComp::from_plugin(&plugin).unwrap();
+        comp.set_threshold_db(-18.0);
+        comp.detector_input = DetectorInput::Main;
+        comp.write_to(&mut plugin);
+        plugin.write_to(&mut element);
+
+        let reread = ReaComp::from_plugin(&VstPlugin::from_element(&element).unwrap()).unwrap();
+        assert_float_absolute_eq!(reread.threshold_db(), -18.0, 1e-9);
+        assert!(!reread.detector_input.is_sidechain());
+        assert_eq!(reread, comp);
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use crate::reaper::{amplitude_to_db, Channels, Project, RouteKind, RoutingEdge, RoutingNode, SendMode, TrackNode};
+
+fn node_id(node: RoutingNode) -> String {
+    match node {
+        RoutingNode::Track(index) => format!("track{index}"),
+        RoutingNode::Master => "master".to_string(),
+        RoutingNode::HardwareOut(channel) => format!("hw{channel}"),
+    }
+}
+
+/// A DOT string literal.
+fn quote(s: &str) -> String {
+    let mut rv = String::with_capacity(s.len() + 2);
+    rv.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => rv.push_str("\\\""),
+            '\\' => rv.push_str("\\\\"),
+            '\n' => rv.push_str("\\n"),
+            c => rv.push(c),
+        }
+    }
+    rv.push('"');
+    rv
+}
+
+/// Channels counted from 1, the way REAPER shows them: `3`, `3/4` or `3-6`.
+fn channel_range(offset: u32, count: u32) -> String {
+    match count {
+        0 | 1 => format!("{}", offset + 1),
+        2 => format!("{}/{}", offset + 1, offset + 2),
+        n => format!("{}-{}", offset + 1, offset + n),
+    }
+}
+
+fn edge_label(edge: &RoutingEdge) -> String {
+    let params = &edge.params;
+    let mut lines = vec![];
+
+    let db = amplitude_to_db(params.volume);
+    let mut level = if db.is_finite() { format!("{db:+.1} dB") } else { "-inf dB".to_string() };
+    match params.mode {
+        SendMode::PostFader => {}
+        SendMode::PreFx => level.push_str(" pre-FX"),
+        SendMode::PreFader => level.push_str(" pre-fader"),
+        SendMode::Other(mode) => write!(level, " mode {mode}").unwrap(),
+    }
+    if params.pan != 0.0 {
+        let side = if params.pan < 0.0 { 'L' } else { 'R' };
+        write!(level, ", {:.0}%{side}", params.pan.abs() * 100.0).unwrap();
+    }
+    if params.muted {
+        level.push_str(", muted");
+    }
+    lines.push(level);
+
+    lines.push(match params.source {
+        Some(Channels { offset, count }) => {
+            format!("{} → {}", channel_range(offset, count), channel_range(params.dest_offset, count))
+        }
+        None => "MIDI only".to_string(),
+    });
+    lines.join("\n")
+}
+
+impl<'a> Project<'a> {
+    /// The tracks, folders, master and hardware outputs as a Graphviz DOT graph. Folders are drawn
+    /// as clusters around their tracks, and each route is an edge labelled with its level, mode
+    /// and channel mapping; parent sends are drawn bold and muted routes dashed.
+    pub fn to_dot(&self) -> String {
+        fn write_nodes(out: &mut String, nodes: &[TrackNode], depth: usize) {
+            let indent = "  ".repeat(depth);
+            for node in nodes {
+                let name = match node.track.name() {
+                    Some(name) if !name.is_empty() => name.to_string(),
+                    _ => format!("Track {}", node.index + 1),
+                };
+                if node.is_folder() {
+                    writeln!(out, "{indent}subgraph cluster_{} {{", node.index).unwrap();
+                    writeln!(out, "{indent}  label={};", quote(&name)).unwrap();
+                    writeln!(out, "{indent}  {} [label={}, style=bold];", node_id(RoutingNode::Track(node.index)), quote(&name)).unwrap();
+                    write_nodes(out, &node.children, depth + 1);
+                    writeln!(out, "{indent}}}").unwrap();
+                } else {
+                    writeln!(out, "{indent}{} [label={}];", node_id(RoutingNode::Track(node.index)), quote(&name)).unwrap();
+                }
+            }
+        }
+
+        let graph = self.routing_graph();
+        let mut out = String::from("digraph routing {\n  rankdir=LR;\n  node [shape=box];\n");
+        write_nodes(&mut out, &self.track_tree(), 1);
+        for node in graph.nodes() {
+            match node {
+                RoutingNode::Track(_) => {}
+                RoutingNode::Master => writeln!(out, "  master [label=\"MASTER\", shape=doubleoctagon];").unwrap(),
+                RoutingNode::HardwareOut(channel) => {
+                    writeln!(out, "  {} [label=\"HW out {}\", shape=cds];", node_id(node), channel + 1).unwrap()
+                }
+            }
+        }
+        for edge in &graph.edges {
+            let mut attrs = vec![format!("label={}", quote(&edge_label(edge)))];
+            if edge.kind == RouteKind::ParentSend {
+                attrs.push("style=bold".to_string());
+            } else if edge.params.muted {
+                attrs.push("style=dashed".to_string());
+            }
+            writeln!(out, "  {} -> {} [{}];", node_id(edge.from), node_id(edge.to), attrs.join(", ")).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse_project;
+
+    #[test]
+    fn dot_graph() {
+        let input = r#"<REAPER_PROJECT 0.1 "7.0/linux-x86_64" 1700000000
+  MASTERHWOUT 0 0 1 0 0 0 0 -1
+  <TRACK
+    NAME 'Drums "bus"'
+    ISBUS 1 1
+    MAINSEND 1 0
+  >
+  <TRACK
+    NAME Kick
+    ISBUS 2 -1
+    MAINSEND 1 0
+  >
+  <TRACK
+    NAME ""
+    MAINSEND 0 0
+    AUXRECV 1 3 0.5 -0.5 1 0 0 2050 2 -1:U 4177951 -1 ''
+    AUXRECV 0 0 1 0 0 0 0 -1 0 -1:U 0 -1 ''
+    HWOUT 1028 0 0 0 0 0 1024 -1:U -1
+  >
+>
+"#;
+        let project = parse_project(input).unwrap();
+        assert_eq!(
+            project.to_dot(),
+            r#"digraph routing {
+  rankdir=LR;
+  node [shape=box];
+  subgraph cluster_0 {
+    label="Drums \"bus\"";
+    track0 [label="Drums \"bus\"", style=bold];
+    track1 [label="Kick"];
+  }
+  track2 [label="Track 3"];
+  master [label="MASTER", shape=doubleoctagon];
+  hw0 [label="HW out 1", shape=cds];
+  hw4 [label="HW out 5", shape=cds];
+  track0 -> master [label="+0.0 dB\n1/2 → 1/2", style=bold];
+  track1 -> track0 [label="+0.0 dB\n1/2 → 1/2", style=bold];
+  track1 -> track2 [label="-6.0 dB pre-fader, 50%L, muted\n3-6 → 3-6", style=dashed];
+  track0 -> track2 [label="+0.0 dB\nMIDI only"];
+  track2 -> hw4 [label="-inf dB\n1 → 5"];
+  master -> hw0 [label="+0.0 dB\n1/2 → 1/2"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn folder_closing_too_many_levels() {
+        let input = "<REAPER_PROJECT\n  <TRACK\n    NAME a\n    ISBUS 1 2\n  >\n  <TRACK\n    NAME b\n  >\n  <TRACK\n    NAME c\n    ISBUS 2 -2\n  >\n  <TRACK\n    NAME d\n  >\n>\n";
+        let dot = parse_project(input).unwrap().to_dot();
+        assert_eq!(dot.matches("subgraph").count(), 1);
+        assert!(dot.contains("  }\n  track3 [label=\"d\"];\n"));
+    }
+}
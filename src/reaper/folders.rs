@@ -0,0 +1,321 @@
+use std::mem;
+
+use crate::reaper::routing::renumber_sends;
+use crate::reaper::{Project, Track};
+use crate::{RElement, RFragment, RValue, RValues};
+
+/// A track and the tracks inside it, if it is a folder.
+pub struct TrackNode<'a> {
+    /// Index of the track in [`Project::tracks`].
+    pub index: usize,
+    pub track: Track<'a>,
+    pub children: Vec<TrackNode<'a>>,
+}
+
+impl<'a> TrackNode<'a> {
+    pub fn is_folder(&self) -> bool {
+        !self.children.is_empty()
+    }
+}
+
+/// The folder depth change of a track: 1 for a folder, -n for the last track of n folders.
+fn depth_change(track: &RElement) -> i64 {
+    let values = track.attr_values("ISBUS").unwrap_or_default();
+    match (values.first().and_then(RValue::get_i64), values.get(1).and_then(RValue::get_i64)) {
+        (_, Some(change)) => change,
+        (Some(1), None) => 1,
+        _ => 0,
+    }
+}
+
+/// One entry per track: its position in the project's track list before an edit, and its
+/// folder level after it.
+type Layout = Vec<(usize, usize)>;
+
+/// The end of the subtree starting at `index`: the first later track that is not inside it.
+fn subtree_end(layout: &Layout, index: usize) -> usize {
+    let level = layout[index].1;
+    layout[index + 1..].iter().position(|&(_, l)| l <= level).map_or(layout.len(), |i| index + 1 + i)
+}
+
+/// Takes the track at `index` and the tracks inside it out of `layout`, with their levels made
+/// relative to the track's own level.
+fn take_subtree(layout: &mut Layout, index: usize) -> Layout {
+    let level = layout[index].1;
+    let end = subtree_end(layout, index);
+    layout.drain(index..end).map(|(track, l)| (track, l - level)).collect()
+}
+
+fn put_subtree(layout: &mut Layout, at: usize, subtree: Layout, level: usize) {
+    layout.splice(at..at, subtree.into_iter().map(|(track, l)| (track, l + level)));
+}
+
+impl<'a> Project<'a> {
+    /// Folder level of each track, 0 for top-level tracks, from the `ISBUS` depth changes.
+    /// Closing more folders than are open is ignored, the way REAPER does, and a track is never
+    /// more than one level deeper than the one before it.
+    pub(crate) fn track_levels(&self) -> Vec<usize> {
+        let mut level = 0i64;
+        self.0
+            .children_with_tag("TRACK")
+            .map(|track| {
+                let rv = level as usize;
+                level = (level + depth_change(track)).clamp(0, level + 1);
+                rv
+            })
+            .collect()
+    }
+
+    fn track_layout(&self) -> Layout {
+        self.track_levels().into_iter().enumerate().collect()
+    }
+
+    /// The folder hierarchy of the flat track list. Returns the top-level tracks.
+    pub fn track_tree(&self) -> Vec<TrackNode<'_>> {
+        fn build<'a>(tracks: &mut std::iter::Peekable<impl Iterator<Item=(usize, (Track<'a>, usize))>>, level: usize) -> Vec<TrackNode<'a>> {
+            let mut rv = vec![];
+            while let Some((index, (track, _))) = tracks.next_if(|(_, (_, l))| *l == level) {
+                let children = match tracks.peek() {
+                    Some((_, (_, l))) if *l > level => build(tracks, level + 1),
+                    _ => vec![],
+                };
+                rv.push(TrackNode { index, track, children });
+            }
+            rv
+        }
+        let mut tracks = self.tracks().into_iter().zip(self.track_levels()).enumerate().peekable();
+        build(&mut tracks, 0)
+    }
+
+    /// Puts the tracks back in the order of `layout` and rewrites `ISBUS` wherever the folder
+    /// structure changed, and `AUXRECV` wherever a send's source track moved. Tracks left out of
+    /// `layout` are removed and returned, and so are the sends they fed.
+    fn apply_track_layout(&mut self, layout: Layout) -> Vec<RElement<'a>> {
+        let slots: Vec<usize> = self
+            .0
+            .content
+            .iter()
+            .enumerate()
+            .filter(|(_, frag)| matches!(frag, RFragment::Child(c) if c.tag == "TRACK"))
+            .map(|(i, _)| i)
+            .collect();
+        let mut tracks: Vec<Option<RElement<'a>>> = slots
+            .iter()
+            .map(|&slot| match mem::replace(&mut self.0.content[slot], RFragment::Empty) {
+                RFragment::Child(track) => Some(track),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        let mut new_index = vec![None; tracks.len()];
+        for (i, &(track, _)) in layout.iter().enumerate() {
+            new_index[track] = Some(i);
+        }
+
+        for (i, &(track, level)) in layout.iter().enumerate() {
+            let mut track = tracks[track].take().expect("each track is placed once");
+            renumber_sends(&mut track, &new_index);
+            let next_level = layout.get(i + 1).map_or(0, |&(_, l)| l);
+            let change = next_level as i64 - level as i64;
+            let kind = match change {
+                0 => 0,
+                1 => 1,
+                _ => 2,
+            };
+            let isbus = RValues::ints([kind, change]);
+            if track.attr_values("ISBUS") != Some(&isbus[..]) {
+                track.set_attr("ISBUS", isbus);
+            }
+            self.0.content[slots[i]] = RFragment::Child(track);
+        }
+        for &slot in slots[layout.len()..].iter().rev() {
+            self.0.splice_content(slot..slot + 1, []);
+        }
+        tracks.into_iter().flatten().collect()
+    }
+
+    /// Moves track `index`, with the tracks inside it, to the end of folder `folder`, which becomes
+    /// a folder if it was a plain track. Returns `false` if either index is out of range or
+    /// `folder` is the track itself or inside it.
+    pub fn move_track_into(&mut self, index: usize, folder: usize) -> bool {
+        let mut layout = self.track_layout();
+        if index >= layout.len() || folder >= layout.len() || (index..subtree_end(&layout, index)).contains(&folder) {
+            return false;
+        }
+        let subtree = take_subtree(&mut layout, index);
+        let folder = layout.iter().position(|&(track, _)| track == folder).unwrap();
+        let level = layout[folder].1 + 1;
+        let at = subtree_end(&layout, folder);
+        put_subtree(&mut layout, at, subtree, level);
+        self.apply_track_layout(layout);
+        true
+    }
+
+    /// Moves track `index`, with the tracks inside it, out of its folder, placing it right after
+    /// that folder. Returns `false` if the track is out of range or not in a folder.
+    pub fn move_track_out(&mut self, index: usize) -> bool {
+        let mut layout = self.track_layout();
+        if layout.get(index).is_none_or(|&(_, level)| level == 0) {
+            return false;
+        }
+        let level = layout[index].1 - 1;
+        let parent = layout[..index].iter().rposition(|&(_, l)| l == level).unwrap();
+        let subtree = take_subtree(&mut layout, index);
+        let at = subtree_end(&layout, parent);
+        put_subtree(&mut layout, at, subtree, level);
+        self.apply_track_layout(layout);
+        true
+    }
+
+    /// Moves track `index`, with the tracks inside it, in front of track `before`, into the same
+    /// folder as `before`; `None` moves it to the end of the project, outside any folder. Returns
+    /// `false` if either index is out of range or `before` is inside the moved track.
+    pub fn move_track(&mut self, index: usize, before: Option<usize>) -> bool {
+        let mut layout = self.track_layout();
+        let end = match layout.get(index) {
+            Some(_) => subtree_end(&layout, index),
+            None => return false,
+        };
+        if before.is_some_and(|before| before >= layout.len() || (index + 1..end).contains(&before)) {
+            return false;
+        }
+        if before == Some(index) {
+            return true;
+        }
+        let subtree = take_subtree(&mut layout, index);
+        let (at, level) = match before {
+            Some(before) => {
+                let at = layout.iter().position(|&(track, _)| track == before).unwrap();
+                (at, layout[at].1)
+            }
+            None => (layout.len(), 0),
+        };
+        put_subtree(&mut layout, at, subtree, level);
+        self.apply_track_layout(layout);
+        true
+    }
+
+    /// Removes track `index` and, if it is a folder, every track inside it. Returns the removed
+    /// tracks in project order; empty if `index` is out of range.
+    pub fn remove_track(&mut self, index: usize) -> Vec<RElement<'a>> {
+        let mut layout = self.track_layout();
+        if index >= layout.len() {
+            return vec![];
+        }
+        let end = subtree_end(&layout, index);
+        layout.drain(index..end);
+        self.apply_track_layout(layout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse_project;
+
+    use super::*;
+
+    fn project(tracks: &[(&str, i64)]) -> Project<'static> {
+        let mut input = String::from("<REAPER_PROJECT 0.1 \"7.0/linux-x86_64\" 1700000000\n  TEMPO 120 4 4\n");
+        for (name, change) in tracks {
+            let kind = match change {
+                0 => 0,
+                1 => 1,
+                _ => 2,
+            };
+            input.push_str(&format!("  <TRACK\n    NAME {name}\n    ISBUS {kind} {change}\n  >\n"));
+        }
+        input.push_str("  <EXTENSIONS\n  >\n>\n");
+        parse_project(&input).unwrap().into_owned()
+    }
+
+    fn outline(project: &Project) -> String {
+        fn walk(nodes: &[TrackNode], out: &mut Vec<String>) {
+            for node in nodes {
+                let name = node.track.name().unwrap().to_string();
+                if node.is_folder() {
+                    let mut children = vec![];
+                    walk(&node.children, &mut children);
+                    out.push(format!("{name}({})", children.join(" ")));
+                } else {
+                    out.push(name);
+                }
+            }
+        }
+        let mut out = vec![];
+        walk(&project.track_tree(), &mut out);
+        out.join(" ")
+    }
+
+    fn changes(project: &Project) -> Vec<i64> {
+        project.tracks().iter().map(|track| depth_change(track.0)).collect()
+    }
+
+    #[test]
+    fn tree_from_depth_changes() {
+        let project = project(&[("drums", 1), ("kick", 0), ("toms", 1), ("tom1", 0), ("tom2", -2), ("bass", 0), ("keys", 1), ("rhodes", 0)]);
+        assert_eq!(outline(&project), "drums(kick toms(tom1 tom2)) bass keys(rhodes)");
+        let tree = project.track_tree();
+        assert_eq!(tree[0].children[1].children[1].index, 4);
+        assert_eq!(tree[2].index, 6);
+
+        let project = self::project(&[("a", 0), ("b", -3), ("c", 1)]);
+        assert_eq!(outline(&project), "a b c");
+        let project = self::project(&[("a", 2), ("b", 0), ("c", -2), ("d", 0)]);
+        assert_eq!(outline(&project), "a(b c) d");
+        assert!(parse_project(include_str!("../../StreamingPlugin.rpp")).unwrap().track_tree().iter().all(|node| !node.is_folder()));
+    }
+
+    #[test]
+    fn move_into_and_out_of_folders() {
+        let mut project = project(&[("drums", 1), ("kick", 0), ("snare", -1), ("bass", 0), ("keys", 0)]);
+        assert!(project.move_track_into(3, 0));
+        assert_eq!(outline(&project), "drums(kick snare bass) keys");
+        assert_eq!(changes(&project), [1, 0, 0, -1, 0]);
+
+        assert!(project.move_track_into(0, 4));
+        assert_eq!(outline(&project), "keys(drums(kick snare bass))");
+        assert_eq!(changes(&project), [1, 1, 0, 0, -2]);
+        assert!(!project.move_track_into(0, 2));
+        assert!(!project.move_track_into(0, 0));
+
+        assert!(project.move_track_out(3));
+        assert_eq!(outline(&project), "keys(drums(kick bass) snare)");
+        assert!(project.move_track_out(1));
+        assert_eq!(outline(&project), "keys(snare) drums(kick bass)");
+        assert_eq!(changes(&project), [1, -1, 1, 0, -1]);
+        assert!(!project.move_track_out(0));
+        assert!(!project.move_track_out(9));
+    }
+
+    #[test]
+    fn reorder() {
+        let mut project = project(&[("drums", 1), ("kick", 0), ("snare", -1), ("bass", 0), ("keys", 0)]);
+        assert!(project.move_track(3, Some(0)));
+        assert_eq!(outline(&project), "bass drums(kick snare) keys");
+        assert!(project.move_track(1, None));
+        assert_eq!(outline(&project), "bass keys drums(kick snare)");
+        assert!(project.move_track(1, Some(4)));
+        assert_eq!(outline(&project), "bass drums(kick keys snare)");
+        assert_eq!(changes(&project), [0, 1, 0, 0, -1]);
+        assert!(!project.move_track(1, Some(3)));
+        assert!(project.move_track(1, Some(1)));
+
+        let written = project.0.to_string();
+        assert!(written.starts_with("<REAPER_PROJECT 0.1 \"7.0/linux-x86_64\" 1700000000\n  TEMPO 120 4 4\n  <TRACK\n    NAME bass\n    ISBUS 0 0\n  >\n"));
+        assert!(written.ends_with("    NAME snare\n    ISBUS 2 -1\n  >\n  <EXTENSIONS\n  >\n>\n"));
+    }
+
+    #[test]
+    fn remove_folder_with_children() {
+        let mut project = project(&[("drums", 1), ("kick", 0), ("toms", 1), ("tom1", -2), ("bass", 0)]);
+        let removed = project.remove_track(2);
+        assert_eq!(removed.iter().map(|t| t.get_str_attr("NAME", 0).unwrap()).collect::<Vec<_>>(), ["toms", "tom1"]);
+        assert_eq!(outline(&project), "drums(kick) bass");
+        assert_eq!(changes(&project), [1, -1, 0]);
+
+        assert_eq!(project.remove_track(0).len(), 2);
+        assert_eq!(outline(&project), "bass");
+        assert!(project.remove_track(1).is_empty());
+        assert!(project.0.to_string().ends_with("    NAME bass\n    ISBUS 0 0\n  >\n  <EXTENSIONS\n  >\n>\n"));
+    }
+}
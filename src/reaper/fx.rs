@@ -0,0 +1,544 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::reaper::{JsPlugin, PluginStateError, VstPlugin};
+use crate::{Guid, RElement, RFragment, RValue, RValues};
+
+/// Tags of the elements that hold a plugin inside an FX chain.
+const PLUGIN_TAGS: [&str; 8] = ["VST", "AU", "JS", "CLAP", "DX", "LV2", "VIDEO_EFFECT", "CONTAINER"];
+
+/// The plugin element of an [`FxEntry`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Plugin<'a> {
+    Vst(&'a RElement<'a>),
+    /// VST3 plugins share the `<VST` tag; they are told apart by the `{...}` class ID.
+    Vst3(&'a RElement<'a>),
+    Au(&'a RElement<'a>),
+    Js(&'a RElement<'a>),
+    Clap(&'a RElement<'a>),
+    Dx(&'a RElement<'a>),
+    /// LV2 plugins, video processors, FX containers and anything newer.
+    Other(&'a RElement<'a>),
+}
+
+impl<'a> Plugin<'a> {
+    pub fn from_element(element: &'a RElement<'a>) -> Self {
+        match element.tag.as_ref() {
+            "VST" if is_vst3(element) => Plugin::Vst3(element),
+            "VST" => Plugin::Vst(element),
+            "AU" => Plugin::Au(element),
+            "JS" => Plugin::Js(element),
+            "CLAP" => Plugin::Clap(element),
+            "DX" => Plugin::Dx(element),
+            _ => Plugin::Other(element),
+        }
+    }
+
+    pub fn element(&self) -> &'a RElement<'a> {
+        match *self {
+            Plugin::Vst(e) | Plugin::Vst3(e) | Plugin::Au(e) | Plugin::Js(e) | Plugin::Clap(e) | Plugin::Dx(e) | Plugin::Other(e) => e,
+        }
+    }
+
+    /// The display name, e.g. `VST: ReaEQ (Cockos)`, or for JS effects the effect's path.
+    pub fn name(&self) -> Option<&'a str> {
+        self.element().get_str_arg(0)
+    }
+
+    /// The name given to the instance by the user. `None` if it has not been renamed.
+    pub fn custom_name(&self) -> Option<&'a str> {
+        let index = match self {
+            Plugin::Vst(_) | Plugin::Vst3(_) | Plugin::Dx(_) => 3,
+            Plugin::Au(_) | Plugin::Clap(_) => 2,
+            Plugin::Js(_) => 1,
+            Plugin::Other(_) => return None,
+        };
+        self.element().get_str_arg(index).filter(|name| !name.is_empty())
+    }
+
+    /// Decodes a JSFX effect's sliders. `None` for other kinds of plugins.
+    pub fn js(&self) -> Option<Result<JsPlugin, PluginStateError>> {
+        match self {
+            Plugin::Js(e) => Some(JsPlugin::from_element(e)),
+            _ => None,
+        }
+    }
+
+    /// Decodes a VST or VST3 plugin's header and state. `None` for other kinds of plugins.
+    pub fn vst(&self) -> Option<Result<VstPlugin, PluginStateError>> {
+        match self {
+            Plugin::Vst(e) | Plugin::Vst3(e) => Some(VstPlugin::from_element(e)),
+            _ => None,
+        }
+    }
+}
+
+fn is_vst3(element: &RElement) -> bool {
+    element.get_str_arg(4).is_some_and(|id| id.contains('{')) || element.get_str_arg(0).is_some_and(|name| name.starts_with("VST3"))
+}
+
+/// A plugin in an FX chain, along with the attributes REAPER writes next to the plugin element
+/// rather than inside it: `BYPASS` before it, and `PRESETNAME`, `FLOATPOS`, `FXID`, `WAK` and any
+/// `<PARMENV` envelopes after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxEntry<'a> {
+    pub plugin: Plugin<'a>,
+    pub bypassed: bool,
+    pub offline: bool,
+    pub preset_name: Option<&'a str>,
+    /// Position and size of the plugin window, `x y w h`.
+    pub float_pos: Option<[i64; 4]>,
+    /// Whether the plugin window is open, in which case REAPER writes `FLOAT` instead of
+    /// `FLOATPOS`.
+    pub floating: bool,
+    pub fx_id: Option<Guid>,
+    /// Wet/dry mix, from 0 (dry) to 1 (wet).
+    pub wet: f64,
+    /// Keyboard and MIDI input settings of the plugin window.
+    pub wak: Option<&'a [RValue<'a>]>,
+    pub parm_envs: Vec<&'a RElement<'a>>,
+    /// The fragments of the chain element that make up this entry, from `BYPASS` to the last
+    /// attribute before the next entry.
+    pub range: Range<usize>,
+}
+
+impl<'a> FxEntry<'a> {
+    fn new(chain: &'a RElement<'a>, plugin: &'a RElement<'a>, range: Range<usize>) -> Self {
+        let mut entry = FxEntry {
+            plugin: Plugin::from_element(plugin),
+            bypassed: false,
+            offline: false,
+            preset_name: None,
+            float_pos: None,
+            floating: false,
+            fx_id: None,
+            wet: 1.0,
+            wak: None,
+            parm_envs: vec![],
+            range: range.clone(),
+        };
+        for frag in &chain.content[range] {
+            match frag {
+                RFragment::Attribute(name, values) => match name.as_ref() {
+                    "BYPASS" => {
+                        let flag = |index: usize| values.get(index).and_then(RValue::get_bool).unwrap_or_default();
+                        entry.bypassed = flag(0);
+                        entry.offline = flag(1);
+                    }
+                    "PRESETNAME" => entry.preset_name = values.first().and_then(RValue::get_str),
+                    "FLOATPOS" | "FLOAT" => {
+                        entry.floating = name == "FLOAT";
+                        let pos: Vec<_> = values.iter().filter_map(RValue::get_i64).collect();
+                        entry.float_pos = pos.try_into().ok();
+                    }
+                    "FXID" => entry.fx_id = values.first().and_then(RValue::get_guid),
+                    "WET" => entry.wet = values.first().and_then(RValue::get_num).unwrap_or(1.0),
+                    "WAK" => entry.wak = Some(values),
+                    _ => {}
+                },
+                RFragment::Child(child) if child.tag == "PARMENV" => entry.parm_envs.push(child),
+                _ => {}
+            }
+        }
+        entry
+    }
+}
+
+/// An `FXCHAIN`, `FXCHAIN_REC` (input FX) or `MASTERFXLIST` element.
+pub struct FxChain<'a>(pub &'a RElement<'a>);
+
+impl<'a> FxChain<'a> {
+    /// Ranges of the chain's content that make up each entry, and the index of its plugin element.
+    pub(crate) fn entry_ranges(chain: &RElement) -> Vec<(Range<usize>, usize)> {
+        let mut rv: Vec<(Range<usize>, usize)> = vec![];
+        let mut bypass = None;
+        for (i, frag) in chain.content.iter().enumerate() {
+            match frag {
+                RFragment::Attribute(name, _) if name == "BYPASS" => bypass = Some(i),
+                RFragment::Child(child) if PLUGIN_TAGS.contains(&child.tag.as_ref()) => {
+                    let start = bypass.take().unwrap_or(i);
+                    if let Some((last, _)) = rv.last_mut() {
+                        last.end = start;
+                    }
+                    rv.push((start..chain.content.len(), i));
+                }
+                _ => {}
+            }
+        }
+        rv
+    }
+
+    pub fn entries(&self) -> Vec<FxEntry<'a>> {
+        Self::entry_ranges(self.0)
+            .into_iter()
+            .map(|(range, plugin)| match &self.0.content[plugin] {
+                RFragment::Child(plugin) => FxEntry::new(self.0, plugin, range),
+                _ => unreachable!("entry_ranges points at a child element"),
+            })
+            .collect()
+    }
+
+    /// Index of the entry shown in the FX chain window, `None` if the window is closed.
+    pub fn shown_entry(&self) -> Option<usize> {
+        let show = self.0.get_i64_attr("SHOW", 0)?;
+        usize::try_from(show - 1).ok()
+    }
+
+    pub fn last_selected(&self) -> Option<i64> {
+        self.0.get_i64_attr("LASTSEL", 0)
+    }
+
+    pub fn is_docked(&self) -> bool {
+        self.0.get_bool_attr("DOCKED", 0).unwrap_or_default()
+    }
+}
+
+/// Mutable access to an FX chain, keeping each plugin's `BYPASS`, `PRESETNAME` and `FXID`
+/// fragments together with it.
+pub struct FxChainMut<'e, 'a>(pub &'e mut RElement<'a>);
+
+impl<'e, 'a> FxChainMut<'e, 'a> {
+    pub fn len(&self) -> usize {
+        FxChain::entry_ranges(self.0).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn range(&self, index: usize) -> Option<Range<usize>> {
+        FxChain::entry_ranges(self.0).into_iter().nth(index).map(|(range, _)| range)
+    }
+
+    /// Inserts `plugin` as entry `index`, or appends it if `index` is past the end. The plugin is
+    /// preceded by `BYPASS 0 0 0` and followed by `PRESETNAME` (for VST plugins whose state names a
+    /// program), `FLOATPOS`, a freshly generated `FXID` and `WAK`. Returns the new `FXID`.
+    ///
+    /// The plugin's recorded layout is dropped, so that it is indented to fit the chain.
+    pub fn insert(&mut self, index: usize, mut plugin: RElement<'a>) -> Guid {
+        forget_layout(&mut plugin);
+        let fx_id = Guid::generate();
+        let program_name = match Plugin::from_element(&plugin).vst() {
+            Some(Ok(vst)) if !vst.program_name.is_empty() => Some(vst.program_name),
+            _ => None,
+        };
+
+        let mut fragments = vec![
+            RFragment::Attribute("BYPASS".into(), RValues::ints([0, 0, 0])),
+            RFragment::Child(plugin),
+        ];
+        if let Some(program_name) = program_name {
+            fragments.push(RFragment::Attribute("PRESETNAME".into(), vec![RValue::QS(program_name.into())]));
+        }
+        fragments.extend([
+            RFragment::Attribute("FLOATPOS".into(), RValues::ints([0, 0, 0, 0])),
+            RFragment::Attribute("FXID".into(), RValues::guid(fx_id)),
+            RFragment::Attribute("WAK".into(), RValues::ints([0, 0])),
+        ]);
+
+        let index = index.min(self.len());
+        let at = self.range(index).map_or(self.0.content.len(), |range| range.start);
+        self.0.splice_content(at..at, fragments);
+        self.remap_selection(|i| Some(if i >= index { i + 1 } else { i }));
+        fx_id
+    }
+
+    /// Removes entry `index` and returns its fragments, starting with `BYPASS`. If the entry was
+    /// shown the FX chain window is closed, and if it was selected the selection moves to the entry
+    /// now in its place.
+    pub fn remove(&mut self, index: usize) -> Option<Vec<RFragment<'a>>> {
+        let range = self.range(index)?;
+        let removed = self.0.splice_content(range, []);
+        self.remap_selection(|i| match i.cmp(&index) {
+            Ordering::Less => Some(i),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(i - 1),
+        });
+        Some(removed)
+    }
+
+    /// Moves entry `from` so that it becomes entry `to`, along with the selection and the shown
+    /// entry. Returns `false` if either index is out of range.
+    pub fn move_entry(&mut self, from: usize, to: usize) -> bool {
+        let Some(range) = self.range(from) else {
+            return false;
+        };
+        if to >= self.len() {
+            return false;
+        }
+        let fragments = self.0.splice_content(range, []);
+        let at = self.range(to).map_or(self.0.content.len(), |range| range.start);
+        self.0.splice_content(at..at, fragments);
+        self.remap_selection(|i| {
+            Some(match i {
+                i if i == from => to,
+                i if from < i && i <= to => i - 1,
+                i if to <= i && i < from => i + 1,
+                i => i,
+            })
+        });
+        true
+    }
+
+    /// Moves `LASTSEL` and `SHOW` along with the entries they point at, given where each old entry
+    /// index ended up (`None` for a removed entry), and keeps them within the chain.
+    fn remap_selection(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        let len = self.len();
+        if let Some(sel) = self.0.get_i64_attr("LASTSEL", 0).and_then(|sel| usize::try_from(sel).ok()) {
+            let sel = remap(sel).unwrap_or(sel).min(len.saturating_sub(1));
+            self.0.set_attr("LASTSEL", RValues::ints([sel as i64]));
+        }
+        if let Some(shown) = self.0.get_i64_attr("SHOW", 0).and_then(|show| usize::try_from(show - 1).ok()) {
+            let show = remap(shown).filter(|&i| i < len).map_or(0, |i| i + 1);
+            self.0.set_attr("SHOW", RValues::ints([show as i64]));
+        }
+    }
+
+    /// The values of attribute `name` of entry `index`, inserted with `default` values right after
+    /// `after` (or at the start of the entry) if missing.
+    fn entry_attr_mut(&mut self, index: usize, name: &str, default: Vec<RValue<'a>>, after: &str) -> Option<&mut Vec<RValue<'a>>> {
+        let range = self.range(index)?;
+        let find = |content: &[RFragment<'a>], name: &str| {
+            content[range.clone()]
+                .iter()
+                .position(|frag| matches!(frag, RFragment::Attribute(n, _) if n == name))
+                .map(|i| range.start + i)
+        };
+        let at = match find(&self.0.content, name) {
+            Some(at) => at,
+            None => {
+                let at = find(&self.0.content, after).map_or(range.start, |i| i + 1);
+                self.0.insert_attribute_at(at, name.to_string(), default);
+                at
+            }
+        };
+        match &mut self.0.content[at] {
+            RFragment::Attribute(_, values) => Some(values),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_bypass_flag(&mut self, index: usize, flag: usize, value: bool) -> bool {
+        let Some(values) = self.entry_attr_mut(index, "BYPASS", RValues::ints([0, 0, 0]), "") else {
+            return false;
+        };
+        while values.len() <= flag {
+            values.push(RValue::I(0, None));
+        }
+        values[flag] = RValue::I(value as i64, None);
+        true
+    }
+
+    /// Returns `false` if there is no entry `index`.
+    pub fn set_bypassed(&mut self, index: usize, bypassed: bool) -> bool {
+        self.set_bypass_flag(index, 0, bypassed)
+    }
+
+    /// Returns `false` if there is no entry `index`.
+    pub fn set_offline(&mut self, index: usize, offline: bool) -> bool {
+        self.set_bypass_flag(index, 1, offline)
+    }
+
+    /// Sets the wet/dry mix of entry `index`, from 0 (dry) to 1 (wet), written as `WET` after the
+    /// entry's `FXID`. Returns `false` if there is no entry `index`.
+    pub fn set_wet(&mut self, index: usize, wet: f64) -> bool {
+        let Some(values) = self.entry_attr_mut(index, "WET", RValues::floats([1.0, 0.0]), "FXID") else {
+            return false;
+        };
+        match values.first_mut() {
+            Some(value) => *value = RValue::F(wet, None),
+            None => values.push(RValue::F(wet, None)),
+        }
+        true
+    }
+}
+
+fn forget_layout(element: &mut RElement) {
+    element.layout = None;
+    element.children_mut().for_each(forget_layout);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{parse_chunk, parse_project};
+    use crate::reaper::Track;
+
+    use super::*;
+
+    const CHAIN: &str = r#"<TRACK
+  <FXCHAIN
+    SHOW 2
+    LASTSEL 1
+    DOCKED 0
+    BYPASS 0 0 0
+    <VST "VST3: Pro-Q 3 (FabFilter)" "FabFilter Pro-Q 3.vst3" 0 "" 1470120819{72C4DB717A4D459AB97E51745D84B39D} ""
+      c0O6R+5e7f4CAAAAAQAAAAAAAAACAAAAAAAAAAIAAAABAAAAAAAAAAIAAAAAAAAAAAAAAAEAAAAAABAA
+      AEZhY3RvcnkgRGVmYXVsdAAQAAAA
+    >
+    PRESETNAME "Factory Default"
+    FLOATPOS 120 80 900 500
+    FXID {2AB1C0D6-6F2F-5A4B-8D71-7E2B3C4D5E6F}
+    <PARMENV 3 0 1 0.5
+      EGUID {1B0F6B3F-6A40-4C4E-9C3D-1E2F3A4B5C6D}
+      ACT 1 -1
+      PT 0 0.5 0
+    >
+    WAK 0 0
+    BYPASS 1 0 0
+    <JS loser/3BandEQ "Low cut"
+      0 200 0 2000 0 0 - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    >
+    FLOAT 10 20 300 400
+    FXID {0D6F2A1B-2A3B-4C5D-8E9F-0A1B2C3D4E5F}
+    WAK 0 0
+    BYPASS 0 1 0
+    <AU "AU: AUBandpass (Apple)" "Apple: AUBandpass" "" 1635083896 1651532147 1634758764
+      6QMAAAAAAAABAAAAAAAAAAIAAAAAAAAAAgAAAAAAAAABAAAAAAAAAAIAAAAAAAAAAQAAAAAAAAA=
+    >
+    FXID {3C4D5E6F-7A8B-4C9D-8E0F-1A2B3C4D5E6F}
+    WAK 0 0
+    <CLAP "CLAP: Diva (u-he)" com.u-he.Diva ""
+    >
+    <DX "DX: Reverb" reverb.dll 0 "" {87FC0268-9A55-4360-95AA-004A1D9DE26C}
+    >
+  >
+  <FXCHAIN_REC
+    BYPASS 0 0 0
+    <VST "VST: ReaTune (Cockos)" reatune.vst.dylib 0 "Tuner" 1919251566<56535472746E6572656174756E650000> ""
+    >
+  >
+>
+"#;
+
+    #[test]
+    fn entries_and_sibling_attributes() {
+        let track = parse_chunk(CHAIN).unwrap();
+        let chain = Track(&track).fx_chain().unwrap();
+        assert_eq!(chain.shown_entry(), Some(1));
+        assert_eq!(chain.last_selected(), Some(1));
+
+        let entries = chain.entries();
+        assert_eq!(entries.len(), 5);
+
+        let eq = &entries[0];
+        assert_matches!(eq.plugin, Plugin::Vst3(_));
+        assert_eq!(eq.plugin.name(), Some("VST3: Pro-Q 3 (FabFilter)"));
+        assert_eq!(eq.preset_name, Some("Factory Default"));
+        assert_eq!(eq.float_pos, Some([120, 80, 900, 500]));
+        assert!(!eq.floating && !eq.bypassed);
+        assert_eq!(eq.fx_id, Some("{2AB1C0D6-6F2F-5A4B-8D71-7E2B3C4D5E6F}".parse().unwrap()));
+        assert_eq!(eq.parm_envs.len(), 1);
+        assert_eq!(eq.wak.map(<[_]>::len), Some(2));
+        assert_eq!(eq.range, 3..10);
+
+        let js = &entries[1];
+        assert_matches!(js.plugin, Plugin::Js(_));
+        assert_eq!(js.plugin.custom_name(), Some("Low cut"));
+        assert!(js.bypassed && js.floating);
+        assert!(js.plugin.vst().is_none());
+        assert_eq!(js.plugin.js().unwrap().unwrap().sliders[..3], [Some(0.0), Some(200.0), Some(0.0)]);
+
+        assert!(entries[2].offline);
+        assert_matches!(entries[2].plugin, Plugin::Au(_));
+        assert_matches!(entries[3].plugin, Plugin::Clap(_));
+        assert_eq!((entries[3].range.clone(), entries[3].fx_id), (19..20, None));
+        assert_matches!(entries[4].plugin, Plugin::Dx(_));
+
+        let input_fx = Track(&track).rec_fx_chain().unwrap().entries();
+        assert_matches!(input_fx[0].plugin, Plugin::Vst(_));
+        assert_eq!(input_fx[0].plugin.custom_name(), Some("Tuner"));
+    }
+
+    #[test]
+    fn master_fx_chain() {
+        let project = parse_project(include_str!("../../StreamingPlugin.rpp")).unwrap();
+        let entries = project.master_fx_chain().unwrap().entries();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].floating);
+        assert_eq!(entries[0].preset_name, Some("Program 1"));
+        assert_eq!(entries[0].plugin.vst().unwrap().unwrap().program_name, "Program 1");
+
+        assert!(project.tracks()[0].fx_chain().unwrap().entries().is_empty());
+    }
+
+    #[test]
+    fn inject_streaming_plugin() {
+        let template = parse_project(include_str!("../../StreamingPlugin.rpp")).unwrap();
+        let streaming = template.master_fx_chain().unwrap().entries()[0].plugin.element().clone();
+
+        let input = "<REAPER_PROJECT 0.1 \"7.0/win64\" 1700000000\r\n  TEMPO 120 4 4\r\n  <TRACK\r\n  >\r\n>\r\n";
+        let mut project = parse_project(input).unwrap();
+        let fx_id = project.master_fx_chain_mut().insert(0, streaming);
+
+        let written = project.0.to_string();
+        let reparsed = parse_project(&written).unwrap();
+        assert_eq!(reparsed.0.content.iter().position(|f| matches!(f, RFragment::Child(c) if c.tag == "MASTERFXLIST")), Some(1));
+        let entries = reparsed.master_fx_chain().unwrap().entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].fx_id, Some(fx_id));
+        assert_eq!(entries[0].preset_name, Some("Program 1"));
+        assert!(written.contains("\r\n    <VST \"VST: Streaming Plugin (Distopik)\""));
+        assert!(written.contains("\r\n      AFByb2dyYW0gMQAQAAAA\r\n    >\r\n    PRESETNAME \"Program 1\"\r\n"));
+        assert_eq!(reparsed.streaming_configs().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reorder_remove_and_bypass() {
+        let mut track = parse_chunk(CHAIN).unwrap();
+        let chain = track.children_with_tag_mut("FXCHAIN").next().unwrap();
+        let mut chain = FxChainMut(chain);
+        let names = |chain: &RElement| -> Vec<String> {
+            FxChain(chain).entries().iter().map(|e| e.plugin.name().unwrap().to_string()).collect()
+        };
+
+        let inserted = chain.insert(1, RElement::new("JS", RValues::strings(["utility/volume"])));
+        assert_eq!(names(chain.0)[..3], ["VST3: Pro-Q 3 (FabFilter)", "utility/volume", "loser/3BandEQ"]);
+        assert_eq!(FxChain(chain.0).entries()[1].fx_id, Some(inserted));
+        assert_ne!(FxChain(chain.0).entries()[0].fx_id, Some(inserted));
+
+        assert!(chain.move_entry(0, 5));
+        assert_eq!(names(chain.0)[4..], ["DX: Reverb", "VST3: Pro-Q 3 (FabFilter)"]);
+        let moved = &FxChain(chain.0).entries()[5];
+        assert_eq!((moved.preset_name, moved.parm_envs.len()), (Some("Factory Default"), 1));
+        assert!(!chain.move_entry(6, 0));
+
+        assert!(chain.set_bypassed(0, true) && chain.set_offline(3, true) && chain.set_wet(0, 0.25));
+        let entries = FxChain(chain.0).entries();
+        assert!(entries[0].bypassed && !entries[0].offline && entries[0].wet == 0.25);
+        assert!(entries[3].offline && entries[3].wet == 1.0);
+        assert!(!chain.set_bypassed(9, true));
+
+        let removed = chain.remove(0).unwrap();
+        assert_matches!(&removed[..2], [RFragment::Attribute(bypass, _), RFragment::Child(js)] if bypass == "BYPASS" && js.tag == "JS");
+        assert!(removed.iter().any(|f| matches!(f, RFragment::Attribute(name, v) if name == "WET" && v[0] == RValue::F(0.25, None))));
+        while chain.remove(0).is_some() {}
+        assert!(chain.is_empty());
+        assert_eq!((chain.0.get_i64_attr("LASTSEL", 0), chain.0.get_i64_attr("SHOW", 0)), (Some(0), Some(0)));
+    }
+
+    #[test]
+    fn selection_follows_entries() {
+        let mut track = parse_chunk(CHAIN).unwrap();
+        let chain = track.children_with_tag_mut("FXCHAIN").next().unwrap();
+        let mut chain = FxChainMut(chain);
+        let selection = |chain: &FxChainMut| (chain.0.get_i64_attr("LASTSEL", 0), chain.0.get_i64_attr("SHOW", 0));
+
+        assert!(chain.move_entry(1, 0));
+        assert_eq!(selection(&chain), (Some(0), Some(1)));
+        chain.insert(0, RElement::new("JS", RValues::strings(["utility/volume"])));
+        assert_eq!(selection(&chain), (Some(1), Some(2)));
+        assert!(chain.move_entry(0, 5));
+        assert_eq!(selection(&chain), (Some(0), Some(1)));
+
+        chain.0.set_attr("LASTSEL", RValues::ints([5]));
+        chain.0.set_attr("SHOW", RValues::ints([6]));
+        assert!(chain.move_entry(5, 0));
+        assert_eq!(selection(&chain), (Some(0), Some(1)));
+        assert!(chain.move_entry(0, 5));
+        assert_eq!(selection(&chain), (Some(5), Some(6)));
+        assert!(chain.remove(5).is_some());
+        assert_eq!(selection(&chain), (Some(4), Some(0)));
+        assert!(chain.remove(0).is_some());
+        assert_eq!(selection(&chain), (Some(3), Some(0)));
+    }
+}
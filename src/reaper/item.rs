@@ -0,0 +1,325 @@
+use crate::reaper::{amplitude_to_db, clamp_pan, db_to_amplitude, own_fragments_end, set_attr_value};
+use crate::{is_fragment_attribute, Guid, RElement, RValue};
+
+const FADE_DEFAULT: [f64; 7] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+const VOLPAN_DEFAULT: [f64; 4] = [1.0, 0.0, 1.0, -1.0];
+const PLAYRATE_DEFAULT: [f64; 6] = [1.0, 1.0, 0.0, -1.0, 0.0, 0.0025];
+
+code_enum! {
+    /// Curve of a fade, in the order of REAPER's fade shape menu. Fade-outs use the mirror image
+    /// of the same curve.
+    pub enum FadeShape: i64 {
+        Linear = 0,
+        FastStart = 1,
+        FastEnd = 2,
+        FastStartSteep = 3,
+        FastEndSteep = 4,
+        SlowStartEnd = 5,
+        SlowStartEndSteep = 6,
+    }
+}
+
+/// An item's `FADEIN` or `FADEOUT`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fade {
+    /// Length in seconds.
+    pub length: f64,
+    pub shape: FadeShape,
+    /// Bends the shape, from -1 to 1.
+    pub curvature: f64,
+    /// Length of the automatic crossfade with an overlapping item, 0 if there is none. REAPER
+    /// uses it instead of `length` while it is set.
+    pub auto_length: f64,
+}
+
+impl Fade {
+    fn from_values(values: &[RValue]) -> Self {
+        let num = |index: usize| values.get(index).and_then(RValue::get_num);
+        Fade {
+            length: num(1).unwrap_or_default(),
+            shape: FadeShape::from_code(values.first().and_then(RValue::get_i64).unwrap_or(1)),
+            curvature: num(5).unwrap_or_default(),
+            auto_length: num(2).unwrap_or_default(),
+        }
+    }
+}
+
+/// An `ITEM` element, either a child of a track or a chunk from `GetItemStateChunk`.
+///
+/// `NAME`, `VOLPAN` and `PLAYRATE` are those of the first take, which REAPER writes before any
+/// `TAKE` line. Getters return what REAPER assumes for a new item when an attribute is missing.
+pub struct Item<'a>(pub &'a RElement<'a>);
+
+impl<'a> Item<'a> {
+    /// The values of attribute `name` of the item itself, not of a later take.
+    fn attr(&self, name: &str) -> Option<&'a [RValue<'a>]> {
+        let own = &self.0.content[..own_fragments_end(self.0)];
+        own.iter().find_map(is_fragment_attribute(name)).map(Vec::as_slice)
+    }
+
+    fn value(&self, name: &str, index: usize) -> Option<&'a RValue<'a>> {
+        self.attr(name)?.get(index)
+    }
+
+    fn num(&self, name: &str, index: usize, default: f64) -> f64 {
+        self.value(name, index).and_then(RValue::get_num).unwrap_or(default)
+    }
+
+    fn flag(&self, name: &str, index: usize, default: bool) -> bool {
+        self.value(name, index).and_then(RValue::get_bool).unwrap_or(default)
+    }
+
+    /// Start of the item on the timeline, in seconds.
+    pub fn position(&self) -> f64 {
+        self.num("POSITION", 0, 0.0)
+    }
+
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> Option<f64> {
+        self.value("LENGTH", 0).and_then(RValue::get_num)
+    }
+
+    /// End of the item on the timeline, `position() + len()`.
+    pub fn end(&self) -> f64 {
+        self.position() + self.len().unwrap_or_default()
+    }
+
+    /// The snap offset, in seconds from the start of the item.
+    pub fn snap_offset(&self) -> f64 {
+        self.num("SNAPOFFS", 0, 0.0)
+    }
+
+    /// Whether the source loops when the item is longer than it.
+    pub fn is_looped(&self) -> bool {
+        self.flag("LOOP", 0, true)
+    }
+
+    pub fn fade_in(&self) -> Fade {
+        Fade::from_values(self.attr("FADEIN").unwrap_or_default())
+    }
+
+    pub fn fade_out(&self) -> Fade {
+        Fade::from_values(self.attr("FADEOUT").unwrap_or_default())
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.flag("MUTE", 0, false)
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.num("VOLPAN", 0, 1.0)
+    }
+
+    pub fn volume_db(&self) -> f64 {
+        amplitude_to_db(self.volume())
+    }
+
+    pub fn pan(&self) -> f64 {
+        self.num("VOLPAN", 1, 0.0)
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.flag("SEL", 0, false)
+    }
+
+    pub fn guid(&self) -> Option<Guid> {
+        self.value("IGUID", 0).and_then(RValue::get_guid)
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        self.value("NAME", 0).and_then(RValue::get_str)
+    }
+
+    /// Playback rate, 1 being the source's own speed.
+    pub fn playrate(&self) -> f64 {
+        self.num("PLAYRATE", 0, 1.0)
+    }
+
+    /// Whether the pitch is kept when the playback rate changes.
+    pub fn preserves_pitch(&self) -> bool {
+        self.flag("PLAYRATE", 1, true)
+    }
+
+    /// Pitch adjustment in semitones.
+    pub fn pitch(&self) -> f64 {
+        self.num("PLAYRATE", 2, 0.0)
+    }
+}
+
+/// Mutable access to an `ITEM` element. Like [`TrackMut`](crate::reaper::TrackMut), setters only
+/// touch the values they are about and add missing attributes with REAPER's defaults.
+pub struct ItemMut<'e, 'a>(pub &'e mut RElement<'a>);
+
+impl<'e, 'a> ItemMut<'e, 'a> {
+    pub fn item(&self) -> Item<'_> {
+        Item(self.0)
+    }
+
+    fn set_num(&mut self, name: &str, index: usize, value: f64, default: &[f64]) {
+        set_attr_value(self.0, name, index, RValue::F(value, None), default);
+    }
+
+    fn set_flag(&mut self, name: &str, index: usize, value: bool, default: &[f64]) {
+        set_attr_value(self.0, name, index, RValue::I(value as i64, None), default);
+    }
+
+    pub fn set_position(&mut self, position: f64) {
+        self.set_num("POSITION", 0, position, &[0.0]);
+    }
+
+    pub fn set_length(&mut self, length: f64) {
+        self.set_num("LENGTH", 0, length, &[0.0]);
+    }
+
+    pub fn set_snap_offset(&mut self, offset: f64) {
+        self.set_num("SNAPOFFS", 0, offset, &[0.0]);
+    }
+
+    pub fn set_looped(&mut self, looped: bool) {
+        self.set_flag("LOOP", 0, looped, &[1.0]);
+    }
+
+    fn set_fade(&mut self, name: &str, fade: Fade) {
+        let shape = RValue::I(fade.shape.code(), None);
+        set_attr_value(self.0, name, 0, shape.clone(), &FADE_DEFAULT);
+        self.set_num(name, 1, fade.length, &FADE_DEFAULT);
+        self.set_num(name, 2, fade.auto_length, &FADE_DEFAULT);
+        set_attr_value(self.0, name, 3, shape, &FADE_DEFAULT);
+        self.set_num(name, 5, fade.curvature, &FADE_DEFAULT);
+    }
+
+    pub fn set_fade_in(&mut self, fade: Fade) {
+        self.set_fade("FADEIN", fade);
+    }
+
+    pub fn set_fade_out(&mut self, fade: Fade) {
+        self.set_fade("FADEOUT", fade);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.set_flag("MUTE", 0, muted, &[0.0, 0.0]);
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        self.set_num("VOLPAN", 0, volume, &VOLPAN_DEFAULT);
+    }
+
+    pub fn set_volume_db(&mut self, db: f64) {
+        self.set_volume(db_to_amplitude(db));
+    }
+
+    pub fn set_pan(&mut self, pan: f64) {
+        self.set_num("VOLPAN", 1, clamp_pan(pan), &VOLPAN_DEFAULT);
+    }
+
+    pub fn set_selected(&mut self, selected: bool) {
+        self.set_flag("SEL", 0, selected, &[0.0]);
+    }
+
+    pub fn set_guid(&mut self, guid: Guid) {
+        set_attr_value(self.0, "IGUID", 0, RValue::G(guid), &[]);
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        set_attr_value(self.0, "NAME", 0, RValue::QS(name.to_string().into()), &[]);
+    }
+
+    pub fn set_playrate(&mut self, rate: f64) {
+        self.set_num("PLAYRATE", 0, rate, &PLAYRATE_DEFAULT);
+    }
+
+    pub fn set_preserve_pitch(&mut self, preserve: bool) {
+        self.set_flag("PLAYRATE", 1, preserve, &PLAYRATE_DEFAULT);
+    }
+
+    pub fn set_pitch(&mut self, semitones: f64) {
+        self.set_num("PLAYRATE", 2, semitones, &PLAYRATE_DEFAULT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_float_eq::*;
+
+    use crate::parser::{parse_chunk, parse_project};
+
+    use super::*;
+
+    #[test]
+    fn first_take_values_come_before_take() {
+        let input = "<ITEM\n  POSITION 1\n  VOLPAN 0.5 0.25 1 -1\n  TAKE\n  NAME other\n  VOLPAN 1 0 1 -1\n>";
+        let mut element = parse_chunk(input).unwrap();
+        let item = Item(&element);
+        assert_eq!(item.name(), None);
+        assert_float_absolute_eq!(item.volume_db(), -6.0206, 0.0001);
+        assert_eq!(item.pan(), 0.25);
+
+        let mut item = ItemMut(&mut element);
+        item.set_name("first");
+        item.set_pitch(3.0);
+        item.set_pan(-2.0);
+        assert_eq!((item.item().name(), item.item().pan()), (Some("first"), -1.0));
+        assert_eq!(
+            element.to_string(),
+            "<ITEM\n  POSITION 1\n  VOLPAN 0.5 -1 1 -1\n  NAME \"first\"\n  PLAYRATE 1 1 3 -1 0 0.0025\n  TAKE\n  NAME other\n  VOLPAN 1 0 1 -1\n>"
+        );
+    }
+
+    #[test]
+    fn fades() {
+        let mut element = parse_chunk("<ITEM\n  FADEIN 2 0.1 0.05 2 0 -0.5 0\n  FADEOUT 1 0.01\n>").unwrap();
+        let item = Item(&element);
+        assert_eq!(item.fade_in(), Fade { length: 0.1, shape: FadeShape::FastEnd, curvature: -0.5, auto_length: 0.05 });
+        assert_eq!(item.fade_out(), Fade { length: 0.01, shape: FadeShape::FastStart, curvature: 0.0, auto_length: 0.0 });
+
+        let mut item = ItemMut(&mut element);
+        item.set_fade_in(Fade { length: 0.5, shape: FadeShape::Linear, curvature: 0.0, auto_length: 0.0 });
+        item.set_fade_out(Fade { length: 1.0, shape: FadeShape::FastStartSteep, curvature: 0.5, auto_length: 0.0 });
+        assert_eq!(element.to_string(), "<ITEM\n  FADEIN 0 0.5 0 0 0 0 0\n  FADEOUT 3 1 0 3 0 0.5\n>");
+    }
+
+    #[test]
+    fn flags_guid_and_snap_offset() {
+        let input = "<ITEM\n  POSITION 1\n  SNAPOFFS 0\n  LOOP 1\n  MUTE 0 0\n  SEL 0\n  IGUID {9F1E2D3C-4B5A-4697-8877-665544332211}\n>";
+        let mut element = parse_chunk(input).unwrap();
+        let item = Item(&element);
+        assert_eq!(item.guid(), Some("{9F1E2D3C-4B5A-4697-8877-665544332211}".parse().unwrap()));
+        assert!(item.is_looped() && !item.is_muted() && !item.is_selected());
+
+        let guid = Guid::generate();
+        let mut item = ItemMut(&mut element);
+        item.set_snap_offset(0.25);
+        item.set_looped(false);
+        item.set_muted(true);
+        item.set_selected(true);
+        item.set_guid(guid);
+        let item = item.item();
+        assert_eq!((item.snap_offset(), item.guid()), (0.25, Some(guid)));
+        assert!(!item.is_looped() && item.is_muted() && item.is_selected());
+        assert_eq!(
+            element.to_string(),
+            format!("<ITEM\n  POSITION 1\n  SNAPOFFS 0.25\n  LOOP 0\n  MUTE 1 0\n  SEL 1\n  IGUID {guid}\n>")
+        );
+    }
+
+    #[test]
+    fn missing_values_are_those_of_a_new_item() {
+        let empty = parse_chunk("<ITEM\n>").unwrap();
+        let item = Item(&empty);
+        assert_eq!((item.position(), item.len(), item.end(), item.volume(), item.playrate()), (0.0, None, 0.0, 1.0, 1.0));
+        assert!(item.is_looped() && item.preserves_pitch() && !item.is_muted());
+        assert_eq!(item.fade_in(), Fade { length: 0.0, shape: FadeShape::FastStart, curvature: 0.0, auto_length: 0.0 });
+    }
+
+    #[test]
+    fn moving_an_item_in_a_project() {
+        let mut project = parse_project(include_str!("../../StreamingPlugin.rpp")).unwrap();
+        let item = &project.tracks()[1].items()[0];
+        assert_eq!((item.position(), item.end(), item.name()), (0.0, 25.41866666666667, Some("UREI_Bass.wav")));
+
+        project.tracks_mut()[1].items_mut()[0].set_position(10.0);
+        let written = project.0.to_string();
+        assert!(written.contains("\r\n      POSITION 10\r\n      SNAPOFFS 0\r\n"));
+        assert_eq!(parse_project(&written).unwrap().tracks()[1].items()[0].end(), 35.41866666666667);
+    }
+}
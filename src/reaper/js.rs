@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+
+use crate::parser::parse_values;
+use crate::reaper::PluginStateError;
+use crate::{RElement, RFragment, RValue};
+
+/// A decoded `<JS` element: a JSFX effect and its slider values.
+///
+/// REAPER writes the sliders on the first body line, one token per slot, with `-` for slots the
+/// effect does not use, followed by the name of the loaded preset, quoted only if it has to be.
+/// Values that are not changed keep their original text when written back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsPlugin {
+    /// Path of the effect relative to the Effects folder, e.g. `utility/volume`.
+    pub path: String,
+    /// Name given to the instance by the user, empty if none.
+    pub custom_name: String,
+    /// Values of `slider1`, `slider2`, ..., `None` for unused slots.
+    pub sliders: Vec<Option<f64>>,
+    pub preset_name: Option<String>,
+    original: Vec<RValue<'static>>,
+    original_preset: Option<RValue<'static>>,
+}
+
+impl JsPlugin {
+    /// An effect with no slider values, for writing into a new `<JS` element.
+    pub fn new(path: impl Into<String>) -> Self {
+        JsPlugin {
+            path: path.into(),
+            custom_name: String::new(),
+            sliders: vec![],
+            preset_name: None,
+            original: vec![],
+            original_preset: None,
+        }
+    }
+
+    pub fn from_element(element: &RElement) -> Result<Self, PluginStateError> {
+        if element.tag != "JS" {
+            return Err(PluginStateError::WrongTag(element.tag.to_string()));
+        }
+        let path = element.get_str_arg(0).ok_or(PluginStateError::InvalidArg(0))?.to_string();
+        let custom_name = element.get_str_arg(1).unwrap_or_default().to_string();
+
+        let line = element.content.iter().find_map(|frag| match frag {
+            RFragment::BinData(line) => Some(line.as_ref()),
+            _ => None,
+        });
+        let mut values = match line {
+            Some(line) => parse_values(line).map_err(|e| PluginStateError::InvalidConfig(e.to_string()))?,
+            None => vec![],
+        };
+        let original_preset = match values.last() {
+            Some(RValue::QS(_)) => values.pop(),
+            Some(RValue::S(name)) if name != "-" => values.pop(),
+            _ => None,
+        };
+        let preset_name = original_preset.as_ref().and_then(RValue::get_str).map(str::to_string);
+        let sliders = values
+            .iter()
+            .map(|value| match value {
+                RValue::S(s) if s == "-" => Ok(None),
+                value => value.get_num().map(Some).ok_or_else(|| PluginStateError::InvalidConfig(format!("unexpected slider value {value}"))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(JsPlugin {
+            path,
+            custom_name,
+            sliders,
+            preset_name,
+            original: values.into_iter().map(RValue::into_owned).collect(),
+            original_preset: original_preset.map(RValue::into_owned),
+        })
+    }
+
+    /// The value of `slider<number>`, counting from 1 like JSFX does.
+    pub fn slider(&self, number: usize) -> Option<f64> {
+        self.sliders.get(number.checked_sub(1)?).copied().flatten()
+    }
+
+    /// Sets `slider<number>`, counting from 1, adding unused slots as needed. Returns `false` for
+    /// slider 0.
+    pub fn set_slider(&mut self, number: usize, value: Option<f64>) -> bool {
+        let Some(index) = number.checked_sub(1) else {
+            return false;
+        };
+        if self.sliders.len() <= index {
+            self.sliders.resize(index + 1, None);
+        }
+        self.sliders[index] = value;
+        true
+    }
+
+    /// Writes the path, custom name, sliders and preset name into `element`, which should be the
+    /// `<JS` element this was read from. Other body lines are left as they are.
+    pub fn write_to(&self, element: &mut RElement) {
+        if element.get_str_arg(0) != Some(self.path.as_str()) {
+            set_arg(element, 0, RValue::S(self.path.clone().into()));
+        }
+        if element.get_str_arg(1) != Some(self.custom_name.as_str()) {
+            set_arg(element, 1, RValue::QS(self.custom_name.clone().into()));
+        }
+
+        let mut tokens: Vec<String> = self
+            .sliders
+            .iter()
+            .enumerate()
+            .map(|(i, slider)| match (slider, self.original.get(i)) {
+                (None, _) => "-".to_string(),
+                (Some(v), Some(original)) if original.get_num() == Some(*v) => original.to_string(),
+                (Some(v), _) => format!("{v:.6}"),
+            })
+            .collect();
+        match (&self.preset_name, &self.original_preset) {
+            (Some(name), Some(original)) if original.get_str() == Some(name.as_str()) => tokens.push(original.to_string()),
+            (Some(name), _) => tokens.push(RValue::QS(name.as_str().into()).to_string()),
+            (None, _) => {}
+        }
+        let line = RFragment::BinData(Cow::Owned(tokens.join(" ")));
+
+        match element.content.iter().position(|frag| matches!(frag, RFragment::BinData(_))) {
+            Some(index) => element.content[index] = line,
+            None => element.insert_fragment_at(0, line),
+        }
+    }
+}
+
+fn set_arg<'a>(element: &mut RElement<'a>, index: usize, value: RValue<'a>) {
+    while element.args.len() < index {
+        element.args.push(RValue::QS("".into()));
+    }
+    match element.args.get_mut(index) {
+        Some(arg) => *arg = value,
+        None => element.args.push(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse_chunk;
+
+    use super::*;
+
+    const INPUT: &str = "<JS utility/volume \"Trim\"\n  0.000000 -6.500000 - - - 1 \"Quiet\"\n  EXTRA\n>\n";
+
+    #[test]
+    fn sliders() {
+        let element = parse_chunk(INPUT).unwrap();
+        let js = JsPlugin::from_element(&element).unwrap();
+        assert_eq!(js.path, "utility/volume");
+        assert_eq!(js.custom_name, "Trim");
+        assert_eq!(js.sliders, [Some(0.0), Some(-6.5), None, None, None, Some(1.0)]);
+        assert_eq!(js.preset_name.as_deref(), Some("Quiet"));
+        assert_eq!((js.slider(2), js.slider(3), js.slider(0), js.slider(99)), (Some(-6.5), None, None, None));
+    }
+
+    #[test]
+    fn write_back() {
+        let mut element = parse_chunk(INPUT).unwrap();
+        let mut js = JsPlugin::from_element(&element).unwrap();
+        js.write_to(&mut element);
+        assert_eq!(element.to_string(), INPUT);
+
+        assert!(js.set_slider(2, Some(-3.0)) && js.set_slider(6, None) && js.set_slider(8, Some(0.25)));
+        assert!(!js.set_slider(0, Some(1.0)));
+        js.preset_name = None;
+        js.write_to(&mut element);
+        assert_eq!(element.to_string(), "<JS utility/volume \"Trim\"\n  0.000000 -3.000000 - - - - - 0.250000\n  EXTRA\n>\n");
+        let reread = JsPlugin::from_element(&element).unwrap();
+        assert_eq!((reread.sliders, reread.preset_name), (js.sliders, None));
+    }
+
+    #[test]
+    fn new_element() {
+        let mut element = RElement::new("JS", vec![]);
+        let mut js = JsPlugin::new("loser/3BandEQ");
+        js.set_slider(1, Some(200.0));
+        js.write_to(&mut element);
+        assert_eq!(element.to_string(), "<JS loser/3BandEQ \"\"\n 200.000000\n>\n");
+
+        assert!(JsPlugin::from_element(&parse_chunk("<JS a \"\"\n  0 x 1\n>").unwrap()).is_err());
+    }
+
+    #[test]
+    fn unquoted_preset_name() {
+        let input = "<JS utility/volume \"\"\n  0.5 - - Quiet\n>\n";
+        let mut element = parse_chunk(input).unwrap();
+        let mut js = JsPlugin::from_element(&element).unwrap();
+        assert_eq!(js.sliders, [Some(0.5), None, None]);
+        assert_eq!(js.preset_name.as_deref(), Some("Quiet"));
+        js.write_to(&mut element);
+        assert_eq!(element.to_string(), input);
+
+        js.preset_name = Some("Loud now".to_string());
+        js.write_to(&mut element);
+        assert_eq!(element.to_string(), "<JS utility/volume \"\"\n  0.5 - - \"Loud now\"\n>\n");
+    }
+}
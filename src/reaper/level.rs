@@ -0,0 +1,15 @@
+/// The level in dB of an amplitude factor, which is how REAPER stores volumes, gains and
+/// thresholds: 1 is 0 dB, 0.5 about -6 dB and 0 negative infinity.
+pub fn amplitude_to_db(amplitude: f64) -> f64 {
+    20.0 * amplitude.log10()
+}
+
+/// The amplitude factor of a level in dB, the inverse of [`amplitude_to_db`].
+pub fn db_to_amplitude(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Limits a pan to what REAPER stores, from -1 (hard left) to 1 (hard right).
+pub(crate) fn clamp_pan(pan: f64) -> f64 {
+    pan.clamp(-1.0, 1.0)
+}
@@ -0,0 +1,216 @@
+pub use cockos::{DetectorInput, EqBand, EqBandType, ReaComp, ReaEq, REACOMP_ID, REAEQ_ID};
+pub use folders::TrackNode;
+pub use fx::{FxChain, FxChainMut, FxEntry, Plugin};
+pub use item::{Fade, FadeShape, Item, ItemMut};
+pub use js::JsPlugin;
+pub use level::{amplitude_to_db, db_to_amplitude};
+pub(crate) use level::clamp_pan;
+pub use routing::{Channels, RouteKind, RoutingEdge, RoutingGraph, RoutingNode, SendMode, SendParams};
+pub use streaming::{StreamingConfig, STREAMING_PLUGIN_ID};
+pub use track::{RecordMonitor, Solo, Track, TrackMut};
+pub use vst::{PluginStateError, VstId, VstPlugin};
+
+use crate::{RElement, RFragment, RValue, RValues};
+
+/// Declares an enum for one of REAPER's numeric codes, with an `Other` variant that keeps the
+/// codes this crate has no name for, and `from_code` and `code` to convert from and to them.
+macro_rules! code_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident: $int:ty {
+            $($(#[$variant_meta:meta])* $variant:ident = $code:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)+
+            /// Codes this crate has no name for.
+            Other($int),
+        }
+
+        impl $name {
+            fn from_code(code: $int) -> Self {
+                match code {
+                    $($code => $name::$variant,)+
+                    code => $name::Other(code),
+                }
+            }
+
+            fn code(self) -> $int {
+                match self {
+                    $($name::$variant => $code,)+
+                    $name::Other(code) => code,
+                }
+            }
+        }
+    };
+}
+
+mod cockos;
+mod dot;
+mod folders;
+mod fx;
+mod item;
+mod js;
+mod level;
+mod routing;
+mod streaming;
+mod track;
+mod vst;
+
+pub struct Project<'a>(pub RElement<'a>);
+
+impl<'a> Project<'a> {
+    pub fn into_owned(self) -> Project<'static> {
+        Project(self.0.into_owned())
+    }
+
+    pub fn reaper_version(&self) -> Option<&str> {
+        self.0.get_str_arg(1)
+    }
+
+    pub fn tracks(&self) -> Vec<Track<'_>> {
+        self.0.children_with_tag("TRACK").map(Track).collect()
+    }
+
+    pub fn tracks_mut(&mut self) -> Vec<TrackMut<'_, 'a>> {
+        self.0.children_with_tag_mut("TRACK").map(TrackMut).collect()
+    }
+
+    pub fn master_fx_chain(&self) -> Option<FxChain<'_>> {
+        self.0.children_with_tag("MASTERFXLIST").next().map(FxChain)
+    }
+
+    /// The master FX chain, created empty where REAPER would write it if the project has none.
+    pub fn master_fx_chain_mut(&mut self) -> FxChainMut<'_, 'a> {
+        let existing = self.0.content.iter().position(|frag| matches!(frag, RFragment::Child(c) if c.tag == "MASTERFXLIST"));
+        let index = existing.unwrap_or_else(|| {
+            let followers = ["MASTERPLAYSPEEDENV", "TEMPOENVEX", "PROJBAY", "TRACK"];
+            let at = self
+                .0
+                .content
+                .iter()
+                .position(|frag| matches!(frag, RFragment::Child(c) if followers.contains(&c.tag.as_ref())))
+                .unwrap_or(self.0.content.len());
+            let mut chain = RElement::new("MASTERFXLIST", vec![]);
+            for name in ["SHOW", "LASTSEL", "DOCKED"] {
+                chain.append_attribute(name, RValues::ints([0]));
+            }
+            self.0.insert_child_at(at, chain);
+            at
+        });
+        match &mut self.0.content[index] {
+            RFragment::Child(chain) => FxChainMut(chain),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The end of the fragments that describe `element` itself: in an item, the first `TAKE` line,
+/// after which the lines belong to the second take.
+pub(crate) fn own_fragments_end(element: &RElement) -> usize {
+    element
+        .content
+        .iter()
+        .position(|frag| matches!(frag, RFragment::Attribute(name, _) if name == "TAKE"))
+        .unwrap_or(element.content.len())
+}
+
+/// Sets value `index` of attribute `name`. `default` holds the values REAPER writes for a new
+/// track or item: the attribute is added with them if the element does not have it, and padded
+/// with them up to `index` if it is too short. Only the element's own fragments (see
+/// [`own_fragments_end`]) are searched, and new attributes go before the first child element
+/// among them.
+pub(crate) fn set_attr_value<'a>(element: &mut RElement<'a>, name: &str, index: usize, value: RValue<'a>, default: &[f64]) {
+    let end = own_fragments_end(element);
+    let own = &mut element.content[..end];
+    let existing = own.iter().position(|frag| matches!(frag, RFragment::Attribute(n, _) if n == name));
+    let at = match existing {
+        Some(at) => at,
+        None => {
+            let at = own.iter().position(|frag| matches!(frag, RFragment::Child(_))).unwrap_or(end);
+            let values = default.iter().map(|&v| RValue::F(v, None)).collect();
+            element.insert_attribute_at(at, name.to_string(), values);
+            at
+        }
+    };
+    let RFragment::Attribute(_, values) = &mut element.content[at] else {
+        unreachable!()
+    };
+    while values.len() <= index {
+        values.push(default.get(values.len()).map_or(RValue::I(0, None), |&v| RValue::F(v, None)));
+    }
+    values[index] = value;
+}
+
+#[cfg(test)]
+mod test {
+    use assert_float_eq::*;
+    use nom::error::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn simple_rpp_version_test() {
+        let input = r#"<REAPER_PROJECT 0.1 "6.42/macOS-arm64" 1640001046
+        >"#;
+        let element = crate::parser::parse_element::<(_, ErrorKind)>(input).unwrap().1;
+
+        let project = Project(element);
+        assert_eq!(project.reaper_version(), Some("6.42/macOS-arm64"))
+    }
+
+    #[test]
+    fn rpp_tracks_test() {
+        let input = r#"<REAPER_PROJECT 0.1 "6.42/macOS-arm64" 1640001046
+        <TRACK
+            NAME "quando una stella"
+        >
+        <TRACK
+            NAME "oltre a queste nubi"
+        >
+        >"#;
+        let element = crate::parser::parse_element::<(_, ErrorKind)>(input).unwrap().1;
+        let project = Project(element);
+        assert_eq!(project.tracks().len(), 2);
+        assert_eq!(project.tracks()[0].name(), Some("quando una stella"));
+        assert_eq!(project.tracks()[1].name(), Some("oltre a queste nubi"));
+    }
+
+    #[test]
+    fn rpp_items_test() {
+        let input = r#"<REAPER_PROJECT 0.1 "6.42/macOS-arm64" 1640001046
+        <TRACK
+            NAME "quando una stella"
+            <ITEM
+                LENGTH 5.01
+            >
+        >
+        >"#;
+        let element = crate::parser::parse_element::<(_, ErrorKind)>(input).unwrap().1;
+
+        let project = Project(element);
+        assert_eq!(project.tracks().len(), 1);
+        assert_eq!(project.tracks()[0].items().len(), 1);
+        assert_float_relative_eq!(project.tracks()[0].items()[0].len().unwrap_or_default(), 5.01, 0.01);
+    }
+
+    #[test]
+    fn rpp_owned_project_test() {
+        fn load() -> Project<'static> {
+            let input = String::from(
+                r#"<REAPER_PROJECT 0.1 "6.42/macOS-arm64" 1640001046
+        <TRACK
+            NAME "quando una stella"
+        >
+        >"#,
+            );
+            Project(crate::parser::parse_element::<(_, ErrorKind)>(&input).unwrap().1).into_owned()
+        }
+
+        let project = load();
+        assert_eq!(project.reaper_version(), Some("6.42/macOS-arm64"));
+        assert_eq!(project.tracks()[0].name(), Some("quando una stella"));
+    }
+}
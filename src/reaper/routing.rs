@@ -0,0 +1,503 @@
+use crate::reaper::Project;
+use crate::{is_fragment_attribute, RElement, RFragment, RValue};
+
+/// A node of a [`RoutingGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RoutingNode {
+    /// A track, by its index in [`Project::tracks`].
+    Track(usize),
+    Master,
+    /// A hardware output, by the index of its first channel.
+    HardwareOut(u32),
+}
+
+/// How an edge of a [`RoutingGraph`] is set up in the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    /// An `AUXRECV` line on the receiving track.
+    Send,
+    /// `MAINSEND`: the track feeds its folder, or the master if it is not in a folder.
+    ParentSend,
+    /// `HWOUT` on a track or `MASTERHWOUT` on the project.
+    HardwareOut,
+}
+
+code_enum! {
+    /// Where a send taps the signal, the second value of `AUXRECV` and `HWOUT`.
+    pub enum SendMode: i64 {
+        PostFader = 0,
+        PreFx = 1,
+        /// After the FX, before the fader.
+        PreFader = 3,
+    }
+}
+
+/// A range of audio channels, counted from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channels {
+    pub offset: u32,
+    pub count: u32,
+}
+
+impl Channels {
+    /// Decodes a source channel value: the offset in the low 10 bits, and above them 0 for a
+    /// stereo pair, 1 for mono, or half the channel count. `None` for -1, which sends no audio.
+    fn decode(v: i64) -> Option<Self> {
+        if v < 0 {
+            return None;
+        }
+        let count = match v >> 10 {
+            0 => 2,
+            1 => 1,
+            n => n as u32 * 2,
+        };
+        Some(Channels { offset: (v & 0x3ff) as u32, count })
+    }
+
+    fn encode(channels: Option<Self>) -> i64 {
+        let Some(Channels { offset, count }) = channels else {
+            return -1;
+        };
+        let width = match count {
+            2 => 0,
+            1 => 1,
+            n => i64::from(n / 2),
+        };
+        width << 10 | i64::from(offset)
+    }
+}
+
+/// Levels and channel mapping of a send.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SendParams {
+    pub mode: SendMode,
+    /// Linear, see [`amplitude_to_db`](crate::amplitude_to_db).
+    pub volume: f64,
+    pub pan: f64,
+    pub muted: bool,
+    /// The channels taken from the sending side, `None` for sends that carry only MIDI.
+    pub source: Option<Channels>,
+    /// The first channel they arrive at on the receiving side.
+    pub dest_offset: u32,
+}
+
+impl Default for SendParams {
+    /// A post-fader stereo send at 0 dB from channels 1/2 to channels 1/2.
+    fn default() -> Self {
+        SendParams {
+            mode: SendMode::PostFader,
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            source: Some(Channels { offset: 0, count: 2 }),
+            dest_offset: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingEdge {
+    pub from: RoutingNode,
+    pub to: RoutingNode,
+    pub kind: RouteKind,
+    pub params: SendParams,
+}
+
+/// The signal flow between the tracks, the master and the hardware outputs of a project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingGraph {
+    /// The number of tracks, which are nodes `Track(0)` to `Track(track_count - 1)`.
+    pub track_count: usize,
+    pub edges: Vec<RoutingEdge>,
+}
+
+impl RoutingGraph {
+    /// Every track, the master, and the hardware outputs that something is routed to.
+    pub fn nodes(&self) -> Vec<RoutingNode> {
+        let mut outputs: Vec<_> = self.edges.iter().map(|edge| edge.to).filter(|to| matches!(to, RoutingNode::HardwareOut(_))).collect();
+        outputs.sort();
+        outputs.dedup();
+        let mut rv: Vec<_> = (0..self.track_count).map(RoutingNode::Track).collect();
+        rv.push(RoutingNode::Master);
+        rv.extend(outputs);
+        rv
+    }
+
+    pub fn edges_from(&self, node: RoutingNode) -> impl Iterator<Item=&RoutingEdge> {
+        self.edges.iter().filter(move |edge| edge.from == node)
+    }
+
+    pub fn edges_to(&self, node: RoutingNode) -> impl Iterator<Item=&RoutingEdge> {
+        self.edges.iter().filter(move |edge| edge.to == node)
+    }
+
+    /// Groups of tracks that feed back into themselves, as track indices in ascending order.
+    /// Muted sends count, since unmuting them would close the loop.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut successors = vec![vec![]; self.track_count];
+        for edge in &self.edges {
+            if let (RoutingNode::Track(from), RoutingNode::Track(to)) = (edge.from, edge.to) {
+                successors[from].push(to);
+            }
+        }
+
+        // Tarjan's strongly connected components.
+        struct State {
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            next: usize,
+            components: Vec<Vec<usize>>,
+        }
+        fn visit(v: usize, successors: &[Vec<usize>], s: &mut State) {
+            s.index[v] = Some(s.next);
+            s.low[v] = s.next;
+            s.next += 1;
+            s.stack.push(v);
+            s.on_stack[v] = true;
+            for &w in &successors[v] {
+                match s.index[w] {
+                    None => {
+                        visit(w, successors, s);
+                        s.low[v] = s.low[v].min(s.low[w]);
+                    }
+                    Some(index) if s.on_stack[w] => s.low[v] = s.low[v].min(index),
+                    Some(_) => {}
+                }
+            }
+            if Some(s.low[v]) == s.index[v] {
+                let mut component = vec![];
+                while let Some(w) = s.stack.pop() {
+                    s.on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                if component.len() > 1 || successors[v].contains(&v) {
+                    component.sort();
+                    s.components.push(component);
+                }
+            }
+        }
+
+        let n = self.track_count;
+        let mut state = State {
+            index: vec![None; n],
+            low: vec![0; n],
+            stack: vec![],
+            on_stack: vec![false; n],
+            next: 0,
+            components: vec![],
+        };
+        for v in 0..n {
+            if state.index[v].is_none() {
+                visit(v, &successors, &mut state);
+            }
+        }
+        state.components.sort();
+        state.components
+    }
+
+    pub fn has_feedback(&self) -> bool {
+        !self.cycles().is_empty()
+    }
+
+    /// Whether audio from track `from` reaches track `to` through sends and folders.
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut seen = vec![false; self.track_count];
+        let mut pending = vec![from];
+        while let Some(track) = pending.pop() {
+            if track == to {
+                return true;
+            }
+            for edge in self.edges_from(RoutingNode::Track(track)) {
+                if let RoutingNode::Track(next) = edge.to {
+                    if !std::mem::replace(&mut seen[next], true) {
+                        pending.push(next);
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+fn num(values: &[RValue], index: usize, default: f64) -> f64 {
+    values.get(index).and_then(RValue::get_num).unwrap_or(default)
+}
+
+fn int(values: &[RValue], index: usize, default: i64) -> i64 {
+    values.get(index).and_then(RValue::get_i64).unwrap_or(default)
+}
+
+/// Reads an `AUXRECV`, `HWOUT` or `MASTERHWOUT` line, given the positions of the source and
+/// destination channels, which differ between them.
+fn send_params(values: &[RValue], source: usize, dest: usize) -> SendParams {
+    SendParams {
+        mode: SendMode::from_code(int(values, 1, 0)),
+        volume: num(values, 2, 1.0),
+        pan: num(values, 3, 0.0),
+        muted: int(values, 4, 0) != 0,
+        source: Channels::decode(int(values, source, 0)),
+        dest_offset: (int(values, dest, 0) & 0x3ff) as u32,
+    }
+}
+
+fn hardware_outs(element: &RElement, name: &str, from: RoutingNode) -> Vec<RoutingEdge> {
+    element
+        .content
+        .iter()
+        .filter_map(is_fragment_attribute(name))
+        .map(|values| {
+            let params = send_params(values, 6, 0);
+            RoutingEdge { from, to: RoutingNode::HardwareOut(params.dest_offset), kind: RouteKind::HardwareOut, params }
+        })
+        .collect()
+}
+
+/// The `AUXRECV` line for a send from track `from`, with MIDI sent on all channels.
+fn auxrecv<'a>(from: usize, params: &SendParams) -> Vec<RValue<'a>> {
+    let mono = if params.source.is_some_and(|source| source.count == 1) { 1024 } else { 0 };
+    vec![
+        RValue::I(from as i64, None),
+        RValue::I(params.mode.code(), None),
+        RValue::F(params.volume, None),
+        RValue::F(params.pan, None),
+        RValue::I(params.muted as i64, None),
+        RValue::I(0, None),
+        RValue::I(0, None),
+        RValue::I(Channels::encode(params.source), None),
+        RValue::I(i64::from(params.dest_offset) | mono, None),
+        RValue::S("-1:U".into()),
+        RValue::I(0, None),
+        RValue::I(-1, None),
+        RValue::QS("".into()),
+    ]
+}
+
+/// Points the `AUXRECV` lines of `track` at the new indices of their source tracks, given by
+/// `new_index`, and drops the ones whose source track is gone.
+pub(crate) fn renumber_sends(track: &mut RElement, new_index: &[Option<usize>]) {
+    track.retain_mut(|frag| {
+        let RFragment::Attribute(name, values) = frag else {
+            return true;
+        };
+        let Some(from) = values.first().and_then(RValue::get_i64).filter(|_| name == "AUXRECV") else {
+            return true;
+        };
+        match usize::try_from(from).ok().and_then(|from| new_index.get(from).copied().flatten()) {
+            Some(to) if to as i64 == from => true,
+            Some(to) => {
+                values[0] = RValue::I(to as i64, None);
+                true
+            }
+            None => false,
+        }
+    });
+}
+
+impl<'a> Project<'a> {
+    /// The sends, parent sends and hardware outputs of the project.
+    pub fn routing_graph(&self) -> RoutingGraph {
+        let tracks: Vec<_> = self.0.children_with_tag("TRACK").collect();
+        let levels = self.track_levels();
+        let mut edges = vec![];
+
+        for (index, track) in tracks.iter().enumerate() {
+            let node = RoutingNode::Track(index);
+            for values in track.content.iter().filter_map(is_fragment_attribute("AUXRECV")) {
+                let Some(from) = values.first().and_then(RValue::get_i64).and_then(|from| usize::try_from(from).ok()) else {
+                    continue;
+                };
+                if from < tracks.len() {
+                    edges.push(RoutingEdge { from: RoutingNode::Track(from), to: node, kind: RouteKind::Send, params: send_params(values, 7, 8) });
+                }
+            }
+
+            let main_send = track.attr_values("MAINSEND").unwrap_or_default();
+            if int(main_send, 0, 1) != 0 {
+                let parent = match levels[index] {
+                    0 => None,
+                    level => levels[..index].iter().rposition(|&l| l == level - 1),
+                };
+                let channels = track.get_i64_attr("NCHAN", 0).unwrap_or(2) as u32;
+                let params = SendParams {
+                    source: Some(Channels { offset: 0, count: channels }),
+                    dest_offset: int(main_send, 1, 0) as u32,
+                    ..SendParams::default()
+                };
+                let to = parent.map_or(RoutingNode::Master, RoutingNode::Track);
+                edges.push(RoutingEdge { from: node, to, kind: RouteKind::ParentSend, params });
+            }
+
+            edges.extend(hardware_outs(track, "HWOUT", node));
+        }
+        edges.extend(hardware_outs(&self.0, "MASTERHWOUT", RoutingNode::Master));
+
+        RoutingGraph { track_count: tracks.len(), edges }
+    }
+
+    /// Adds a send from track `from` to track `to` as an `AUXRECV` line on `to`, after its other
+    /// sends. Returns `false`, leaving the project as it was, if either track does not exist or
+    /// `from` already hears `to`, so that the send would feed `from` back into itself. Loops
+    /// elsewhere in the project do not matter.
+    pub fn add_send(&mut self, from: usize, to: usize, params: SendParams) -> bool {
+        let graph = self.routing_graph();
+        if from >= graph.track_count || to >= graph.track_count || graph.reaches(to, from) {
+            return false;
+        }
+
+        let track = self.0.children_with_tag_mut("TRACK").nth(to).unwrap();
+        let position = |name: &str| track.content.iter().rposition(|frag| matches!(frag, RFragment::Attribute(n, _) if n == name));
+        let at = match position("AUXRECV").or_else(|| position("MAINSEND")) {
+            Some(i) => i + 1,
+            None => track.content.iter().position(|frag| matches!(frag, RFragment::Child(_))).unwrap_or(track.content.len()),
+        };
+        track.insert_attribute_at(at, "AUXRECV", auxrecv(from, &params));
+        true
+    }
+
+    /// Removes every send from track `from` to track `to`, returning how many were removed.
+    pub fn remove_send(&mut self, from: usize, to: usize) -> usize {
+        let Some(track) = self.0.children_with_tag_mut("TRACK").nth(to) else {
+            return 0;
+        };
+        let before = track.content.len();
+        track.retain(|frag| {
+            !matches!(frag, RFragment::Attribute(name, values)
+                if name == "AUXRECV" && values.first().and_then(RValue::get_i64) == Some(from as i64))
+        });
+        before - track.content.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse_project;
+
+    use super::*;
+
+    const INPUT: &str = r#"<REAPER_PROJECT 0.1 "7.0/linux-x86_64" 1700000000
+  MASTERHWOUT 0 0 1 0 0 0 0 -1
+  <TRACK
+    NAME Drums
+    ISBUS 1 1
+    NCHAN 4
+    MAINSEND 1 0
+    AUXRECV 3 3 0.5 -0.5 1 0 0 2050 2 -1:U 4177951 -1 ''
+  >
+  <TRACK
+    NAME Kick
+    ISBUS 2 -1
+    MAINSEND 1 2
+    AUXRECV 0 0 1 0 0 0 0 -1 0 -1:U 0 -1 ''
+  >
+  <TRACK
+    NAME Click
+    MAINSEND 0 0
+    HWOUT 1028 1 0.25 0 0 0 1024 -1:U -1
+  >
+  <TRACK
+    NAME Reverb
+    MAINSEND 1 0
+  >
+>
+"#;
+
+    #[test]
+    fn graph() {
+        let project = parse_project(INPUT).unwrap();
+        let graph = project.routing_graph();
+        assert_eq!(graph.track_count, 4);
+        assert_eq!(
+            graph.nodes(),
+            [RoutingNode::Track(0), RoutingNode::Track(1), RoutingNode::Track(2), RoutingNode::Track(3), RoutingNode::Master, RoutingNode::HardwareOut(0), RoutingNode::HardwareOut(4)]
+        );
+
+        let into_drums: Vec<_> = graph.edges_to(RoutingNode::Track(0)).collect();
+        assert_eq!(into_drums.len(), 2);
+        assert_eq!(
+            *into_drums[0],
+            RoutingEdge {
+                from: RoutingNode::Track(3),
+                to: RoutingNode::Track(0),
+                kind: RouteKind::Send,
+                params: SendParams {
+                    mode: SendMode::PreFader,
+                    volume: 0.5,
+                    pan: -0.5,
+                    muted: true,
+                    source: Some(Channels { offset: 2, count: 4 }),
+                    dest_offset: 2,
+                },
+            }
+        );
+        assert_eq!((into_drums[1].from, into_drums[1].kind, into_drums[1].params.dest_offset), (RoutingNode::Track(1), RouteKind::ParentSend, 2));
+
+        let drums: Vec<_> = graph.edges_from(RoutingNode::Track(0)).collect();
+        assert_eq!(drums.len(), 2);
+        assert_eq!((drums[0].to, drums[0].params.source), (RoutingNode::Master, Some(Channels { offset: 0, count: 4 })));
+        assert_eq!((drums[1].to, drums[1].params.source), (RoutingNode::Track(1), None));
+
+        let click: Vec<_> = graph.edges_from(RoutingNode::Track(2)).collect();
+        assert_eq!(click.len(), 1);
+        assert_eq!((click[0].to, click[0].params.mode, click[0].params.volume), (RoutingNode::HardwareOut(4), SendMode::PreFx, 0.25));
+        assert_eq!(click[0].params.source, Some(Channels { offset: 0, count: 1 }));
+        assert_eq!(graph.edges_from(RoutingNode::Master).map(|e| e.to).collect::<Vec<_>>(), [RoutingNode::HardwareOut(0)]);
+
+        assert_eq!(graph.cycles(), [vec![0, 1]]);
+        assert!(graph.has_feedback());
+    }
+
+    #[test]
+    fn add_and_remove_sends() {
+        let mut project = parse_project(INPUT).unwrap();
+        assert_eq!(project.remove_send(0, 1), 1);
+        assert!(!project.routing_graph().has_feedback());
+
+        assert!(!project.add_send(1, 1, SendParams::default()));
+        assert!(!project.add_send(0, 9, SendParams::default()));
+        assert!(!project.add_send(0, 3, SendParams::default()));
+
+        let params = SendParams { volume: 0.5, source: Some(Channels { offset: 2, count: 1 }), dest_offset: 4, ..SendParams::default() };
+        assert!(project.add_send(2, 3, params));
+        let written = project.0.to_string();
+        assert!(written.contains("    NAME Reverb\n    MAINSEND 1 0\n    AUXRECV 2 0 0.5 0 0 0 0 1026 1028 -1:U 0 -1 \"\"\n  >\n"));
+        let reparsed = parse_project(&written).unwrap();
+        let graph = reparsed.routing_graph();
+        let send = graph.edges_to(RoutingNode::Track(3)).find(|e| e.from == RoutingNode::Track(2)).unwrap();
+        assert_eq!(send.params, params);
+
+        assert!(project.add_send(1, 3, SendParams::default()));
+        assert!(project.0.to_string().contains("\n    AUXRECV 2 0 0.5 0 0 0 0 1026 1028 -1:U 0 -1 \"\"\n    AUXRECV 1 0 1 0 0 0 0 0 0 -1:U 0 -1 \"\"\n"));
+        assert_eq!(project.remove_send(2, 3), 1);
+        assert_eq!(project.remove_send(2, 3), 0);
+    }
+
+    #[test]
+    fn add_send_beside_existing_loop() {
+        let mut project = parse_project(INPUT).unwrap();
+        assert!(project.routing_graph().has_feedback());
+        assert!(project.add_send(2, 3, SendParams::default()));
+        assert!(!project.add_send(1, 3, SendParams::default()));
+        assert!(!project.add_send(3, 2, SendParams::default()));
+        assert_eq!(project.routing_graph().cycles(), [vec![0, 1]]);
+    }
+
+    #[test]
+    fn sends_follow_moved_tracks() {
+        let mut project = parse_project(INPUT).unwrap();
+        assert!(project.move_track(3, Some(0)));
+        let graph = project.routing_graph();
+        let names: Vec<_> = project.tracks().iter().map(|t| t.name().unwrap()).collect();
+        assert_eq!(names, ["Reverb", "Drums", "Kick", "Click"]);
+        assert_eq!(graph.edges_to(RoutingNode::Track(1)).find(|e| e.kind == RouteKind::Send).unwrap().from, RoutingNode::Track(0));
+        assert_eq!(graph.edges_to(RoutingNode::Track(2)).find(|e| e.kind == RouteKind::Send).unwrap().from, RoutingNode::Track(1));
+
+        project.remove_track(0);
+        assert_eq!(project.0.to_string().matches("AUXRECV").count(), 1);
+        assert_eq!(project.routing_graph().edges.iter().filter(|e| e.kind == RouteKind::Send).count(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::parser::{escape_json_string, parse_json_object, unescape_json_string};
+use crate::reaper::{PluginStateError, Project, VstId, VstPlugin};
+use crate::RElement;
+
+/// Unique ID of the Distopik Streaming Plugin.
+pub const STREAMING_PLUGIN_ID: u32 = 54811357;
+
+/// The configuration the Distopik Streaming Plugin keeps as JSON in its plugin chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamingConfig {
+    /// `None` until the project is assigned to a session.
+    pub session_id: Option<String>,
+    pub bits_per_sample: u32,
+    pub desired_sample_rate: u32,
+    /// Members not listed above, with their values as JSON text, written back after them.
+    pub other: Vec<(String, String)>,
+}
+
+impl StreamingConfig {
+    pub fn from_json(json: &str) -> Result<Self, PluginStateError> {
+        let invalid = |reason: String| PluginStateError::InvalidConfig(reason);
+        let members = parse_json_object(json).map_err(|e| invalid(e.to_string()))?;
+
+        let (mut session_id, mut bits_per_sample, mut desired_sample_rate, mut other) = (None, None, None, vec![]);
+        for (key, value) in members {
+            let number = || value.parse::<u32>().map_err(|_| invalid(format!("expected a whole number for {key}, found {value}")));
+            match key.as_str() {
+                "session_id" if value == "null" => session_id = None,
+                "session_id" => {
+                    let id = unescape_json_string(value);
+                    session_id = Some(id.ok_or_else(|| invalid(format!("expected a string or null for session_id, found {value}")))?);
+                }
+                "bits_per_sample" => bits_per_sample = Some(number()?),
+                "desired_sample_rate" => desired_sample_rate = Some(number()?),
+                _ => other.push((key, value.to_string())),
+            }
+        }
+
+        Ok(StreamingConfig {
+            session_id,
+            bits_per_sample: bits_per_sample.ok_or_else(|| invalid("missing bits_per_sample".to_string()))?,
+            desired_sample_rate: desired_sample_rate.ok_or_else(|| invalid("missing desired_sample_rate".to_string()))?,
+            other,
+        })
+    }
+
+    /// Compact JSON, the way the plugin writes it.
+    pub fn to_json(&self) -> String {
+        let session_id = self.session_id.as_deref().map_or("null".to_string(), escape_json_string);
+        let mut rv = format!(
+            "{{\"session_id\":{session_id},\"bits_per_sample\":{},\"desired_sample_rate\":{}",
+            self.bits_per_sample, self.desired_sample_rate,
+        );
+        for (key, value) in &self.other {
+            rv.push(',');
+            rv.push_str(&escape_json_string(key));
+            rv.push(':');
+            rv.push_str(value);
+        }
+        rv.push('}');
+        rv
+    }
+
+    fn from_plugin(plugin: &VstPlugin) -> Result<Self, PluginStateError> {
+        let json = std::str::from_utf8(&plugin.chunk).map_err(|e| PluginStateError::InvalidConfig(e.to_string()))?;
+        Self::from_json(json)
+    }
+}
+
+fn is_streaming_plugin(element: &RElement) -> bool {
+    element.tag == "VST" && VstId::of(element).is_ok_and(|id| id.unique_id == STREAMING_PLUGIN_ID)
+}
+
+impl<'a> Project<'a> {
+    fn streaming_plugins(&self) -> impl Iterator<Item = &RElement<'a>> {
+        let master = self.0.children_with_tag("MASTERFXLIST");
+        let tracks = self.0.children_with_tag("TRACK").flat_map(|track| track.children_with_tag("FXCHAIN"));
+        master.chain(tracks).flat_map(|chain| chain.children_with_tag("VST")).filter(|vst| is_streaming_plugin(vst))
+    }
+
+    fn streaming_plugins_mut(&mut self) -> Vec<&mut RElement<'a>> {
+        let mut rv = vec![];
+        for child in self.0.children_mut() {
+            let chains: Vec<_> = match child.tag.as_ref() {
+                "MASTERFXLIST" => vec![child],
+                "TRACK" => child.children_with_tag_mut("FXCHAIN").collect(),
+                _ => continue,
+            };
+            for chain in chains {
+                rv.extend(chain.children_with_tag_mut("VST").filter(|vst| is_streaming_plugin(vst)));
+            }
+        }
+        rv
+    }
+
+    /// Configurations of the Streaming Plugin instances in the master FX chain and the track FX
+    /// chains, in the order they appear in the project.
+    pub fn streaming_configs(&self) -> Result<Vec<StreamingConfig>, PluginStateError> {
+        self.streaming_plugins()
+            .map(|vst| StreamingConfig::from_plugin(&VstPlugin::from_element(vst)?))
+            .collect()
+    }
+
+    /// Calls `f` with the configuration of each Streaming Plugin instance, in the same order as
+    /// [`Project::streaming_configs`], and re-encodes the plugin states it changed. Returns the
+    /// number of instances found.
+    pub fn update_streaming_configs<F>(&mut self, mut f: F) -> Result<usize, PluginStateError>
+        where
+            F: FnMut(&mut StreamingConfig),
+    {
+        let mut plugins = self.streaming_plugins_mut();
+        let decoded = plugins
+            .iter()
+            .map(|vst| {
+                let plugin = VstPlugin::from_element(vst)?;
+                let config = StreamingConfig::from_plugin(&plugin)?;
+                Ok((plugin, config))
+            })
+            .collect::<Result<Vec<_>, PluginStateError>>()?;
+        for (vst, (mut plugin, config)) in plugins.iter_mut().zip(decoded) {
+            let mut edited = config.clone();
+            f(&mut edited);
+            if edited != config {
+                plugin.chunk = edited.to_json().into_bytes();
+                plugin.write_to(vst);
+            }
+        }
+        Ok(plugins.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{parse_chunk, parse_project};
+    use crate::{RFragment, RValue};
+
+    use super::*;
+
+    const INPUT: &str = include_str!("../../StreamingPlugin.rpp");
+
+    #[test]
+    fn read_config() {
+        let project = parse_project(INPUT).unwrap();
+        let configs = project.streaming_configs().unwrap();
+        assert_eq!(
+            configs,
+            [StreamingConfig {
+                session_id: None,
+                bits_per_sample: 16,
+                desired_sample_rate: 48000,
+                other: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn rewrite_config() {
+        let mut project = parse_project(INPUT).unwrap();
+        let mut track_fx = parse_chunk("<FXCHAIN\n>\n").unwrap().into_owned();
+        let master = project.0.children_with_tag("MASTERFXLIST").next().unwrap();
+        track_fx.append_child(master.children_with_tag("VST").next().unwrap().clone().into_owned());
+        project.0.children_with_tag_mut("TRACK").next().unwrap().append_child(track_fx);
+
+        assert_eq!(project.update_streaming_configs(|_| {}).unwrap(), 2);
+        assert_eq!(project.0.to_string().matches("eyJzZXNzaW9uX2lkIjpudWxs").count(), 2);
+
+        let count = project
+            .update_streaming_configs(|config| {
+                config.session_id = Some("b0c5-\"live\"".to_string());
+                config.desired_sample_rate = 96000;
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let reparsed = parse_project(&project.0.to_string()).unwrap().into_owned();
+        let configs = reparsed.streaming_configs().unwrap();
+        assert_eq!(configs.len(), 2);
+        assert!(configs.iter().all(|c| c.session_id.as_deref() == Some("b0c5-\"live\"") && c.desired_sample_rate == 96000));
+        assert_eq!(configs[0].bits_per_sample, 16);
+    }
+
+    #[test]
+    fn failed_update_changes_nothing() {
+        let mut project = parse_project(INPUT).unwrap();
+        let master = project.0.children_with_tag("MASTERFXLIST").next().unwrap();
+        let mut broken = master.children_with_tag("VST").next().unwrap().clone();
+        broken.args[4] = RValue::I(STREAMING_PLUGIN_ID as i64, None);
+        broken.content = vec![RFragment::BinData("AAAA".into())];
+        project.0.children_with_tag_mut("MASTERFXLIST").next().unwrap().append_child(broken);
+        let before = project.0.to_string();
+
+        assert!(project.streaming_configs().is_err());
+        assert!(project.update_streaming_configs(|config| config.bits_per_sample = 24).is_err());
+        assert_eq!(project.0.to_string(), before);
+    }
+
+    #[test]
+    fn json() {
+        let json = r#"{ "desired_sample_rate": 44100, "session_id": "abc", "gain": [1, 2], "bits_per_sample": 24 }"#;
+        let config = StreamingConfig::from_json(json).unwrap();
+        assert_eq!(config.session_id.as_deref(), Some("abc"));
+        assert_eq!((config.bits_per_sample, config.desired_sample_rate), (24, 44100));
+        assert_eq!(
+            config.to_json(),
+            r#"{"session_id":"abc","bits_per_sample":24,"desired_sample_rate":44100,"gain":[1, 2]}"#
+        );
+
+        assert_eq!(
+            StreamingConfig::from_json(r#"{"session_id":null,"bits_per_sample":16.5,"desired_sample_rate":1}"#),
+            Err(PluginStateError::InvalidConfig("expected a whole number for bits_per_sample, found 16.5".to_string()))
+        );
+        assert!(StreamingConfig::from_json(r#"{"session_id":null}"#).is_err());
+        assert!(StreamingConfig::from_json("{").is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use crate::reaper::{amplitude_to_db, clamp_pan, db_to_amplitude, set_attr_value, FxChain, Item, ItemMut};
+use crate::{Guid, RElement, RValue};
+
+const VOLPAN_DEFAULT: [f64; 5] = [1.0, 0.0, -1.0, -1.0, 1.0];
+const MUTESOLO_DEFAULT: [f64; 3] = [0.0, 0.0, 0.0];
+const REC_DEFAULT: [f64; 7] = [0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+const SHOWINMIX_DEFAULT: [f64; 8] = [1.0, 0.6667, 0.5, 1.0, 0.5, 0.0, -1.0, 0.0];
+
+/// `PEAKCOL` of a track without a custom color.
+const DEFAULT_PEAK_COLOR: i64 = 16576;
+/// Set in `PEAKCOL` when the track has a custom color.
+const CUSTOM_COLOR_FLAG: i64 = 0x100_0000;
+
+code_enum! {
+    /// Solo state, the second value of `MUTESOLO`.
+    pub enum Solo: i64 {
+        Off = 0,
+        /// Soloed, ignoring routing.
+        Solo = 1,
+        SoloInPlace = 2,
+    }
+}
+
+code_enum! {
+    /// Input monitoring of a track, the third value of `REC`.
+    pub enum RecordMonitor: i64 {
+        Off = 0,
+        On = 1,
+        /// "Tape auto style": monitor only while not playing.
+        NotWhenPlaying = 2,
+    }
+}
+
+/// A `TRACK` element, either a child of a project or a chunk from `GetTrackStateChunk`.
+///
+/// Getters return what REAPER assumes for a new track when an attribute is missing.
+pub struct Track<'a>(pub &'a RElement<'a>);
+
+impl<'a> Track<'a> {
+    fn value(&self, name: &str, index: usize) -> Option<&'a RValue<'a>> {
+        self.0.attr_values(name)?.get(index)
+    }
+
+    fn num(&self, name: &str, index: usize, default: f64) -> f64 {
+        self.value(name, index).and_then(RValue::get_num).unwrap_or(default)
+    }
+
+    fn int(&self, name: &str, index: usize, default: i64) -> i64 {
+        self.value(name, index).and_then(RValue::get_i64).unwrap_or(default)
+    }
+
+    fn flag(&self, name: &str, index: usize) -> bool {
+        self.value(name, index).and_then(RValue::get_bool).unwrap_or_default()
+    }
+
+    pub fn name(&self) -> Option<&'a str> {
+        self.0.get_str_attr("NAME", 0)
+    }
+
+    /// The track's GUID from `TRACKID`, or from the element's argument if that is missing.
+    pub fn guid(&self) -> Option<Guid> {
+        self.0.get_guid_attr("TRACKID", 0).or_else(|| self.0.args.first().and_then(RValue::get_guid))
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.num("VOLPAN", 0, 1.0)
+    }
+
+    pub fn volume_db(&self) -> f64 {
+        amplitude_to_db(self.volume())
+    }
+
+    pub fn pan(&self) -> f64 {
+        self.num("VOLPAN", 1, 0.0)
+    }
+
+    /// The track's pan law in dB, i.e. the gain of a center-panned signal. `None` when the track
+    /// uses the project default.
+    pub fn pan_law_db(&self) -> Option<f64> {
+        let law = self.num("VOLPAN", 2, -1.0);
+        (law >= 0.0).then(|| amplitude_to_db(law))
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.flag("MUTESOLO", 0)
+    }
+
+    pub fn solo(&self) -> Solo {
+        Solo::from_code(self.int("MUTESOLO", 1, 0))
+    }
+
+    /// Whether the track keeps playing while other tracks are soloed.
+    pub fn is_solo_defeat(&self) -> bool {
+        self.flag("MUTESOLO", 2)
+    }
+
+    pub fn is_phase_inverted(&self) -> bool {
+        self.0.get_bool_attr("IPHASE", 0).unwrap_or_default()
+    }
+
+    pub fn channel_count(&self) -> u32 {
+        self.0.get_i64_attr("NCHAN", 0).and_then(|n| n.try_into().ok()).unwrap_or(2)
+    }
+
+    /// The raw `PEAKCOL` value, a native color with bit 24 set for custom colors.
+    pub fn peak_color(&self) -> i64 {
+        self.0.get_i64_attr("PEAKCOL", 0).unwrap_or(DEFAULT_PEAK_COLOR)
+    }
+
+    /// The custom track color as `(r, g, b)`, `None` if the track has the default color.
+    pub fn color(&self) -> Option<(u8, u8, u8)> {
+        let color = self.peak_color();
+        (color & CUSTOM_COLOR_FLAG != 0).then_some((color as u8, (color >> 8) as u8, (color >> 16) as u8))
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.0.get_bool_attr("SEL", 0).unwrap_or_default()
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.flag("REC", 0)
+    }
+
+    /// The record input as REAPER encodes it: the input channel index, plus 512 for stereo
+    /// pairs, 1024 for multichannel inputs and 4096 for MIDI inputs.
+    pub fn record_input(&self) -> i64 {
+        self.int("REC", 1, 0)
+    }
+
+    pub fn record_monitor(&self) -> RecordMonitor {
+        RecordMonitor::from_code(self.int("REC", 2, 1))
+    }
+
+    /// The record mode: 0 records the input, 1 the output in stereo, 2 disables recording, and
+    /// higher values select the other output and MIDI modes.
+    pub fn record_mode(&self) -> i64 {
+        self.int("REC", 3, 0)
+    }
+
+    pub fn is_shown_in_mixer(&self) -> bool {
+        self.value("SHOWINMIX", 0).and_then(RValue::get_bool).unwrap_or(true)
+    }
+
+    /// Whether the track is shown in the track control panel (the arrange view).
+    pub fn is_shown_in_tcp(&self) -> bool {
+        self.value("SHOWINMIX", 3).and_then(RValue::get_bool).unwrap_or(true)
+    }
+
+    pub fn items(&self) -> Vec<Item<'a>> {
+        self.0.children_with_tag("ITEM").map(Item).collect()
+    }
+
+    pub fn fx_chain(&self) -> Option<FxChain<'a>> {
+        self.0.children_with_tag("FXCHAIN").next().map(FxChain)
+    }
+
+    /// The input FX chain, applied while recording.
+    pub fn rec_fx_chain(&self) -> Option<FxChain<'a>> {
+        self.0.children_with_tag("FXCHAIN_REC").next().map(FxChain)
+    }
+}
+
+/// Mutable access to a `TRACK` element. Setters only touch the values they are about, so values
+/// this crate does not know keep what was read; missing attributes are added with REAPER's
+/// defaults before the first child element.
+pub struct TrackMut<'e, 'a>(pub &'e mut RElement<'a>);
+
+impl<'e, 'a> TrackMut<'e, 'a> {
+    pub fn track(&self) -> Track<'_> {
+        Track(self.0)
+    }
+
+    fn set_value(&mut self, name: &str, index: usize, value: RValue<'a>, default: &[f64]) {
+        set_attr_value(self.0, name, index, value, default);
+    }
+
+    fn set_flag(&mut self, name: &str, index: usize, value: bool, default: &[f64]) {
+        self.set_value(name, index, RValue::I(value as i64, None), default);
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.0.set_attr("NAME", vec![RValue::QS(name.to_string().into())]);
+    }
+
+    pub fn items_mut(&mut self) -> Vec<ItemMut<'_, 'a>> {
+        self.0.children_with_tag_mut("ITEM").map(ItemMut).collect()
+    }
+
+    /// Sets `TRACKID` and the element's argument, which REAPER keeps equal.
+    pub fn set_guid(&mut self, guid: Guid) {
+        self.set_value("TRACKID", 0, RValue::G(guid), &[]);
+        match self.0.args.first_mut() {
+            Some(arg) => *arg = RValue::G(guid),
+            None => self.0.args.push(RValue::G(guid)),
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        self.set_value("VOLPAN", 0, RValue::F(volume, None), &VOLPAN_DEFAULT);
+    }
+
+    pub fn set_volume_db(&mut self, db: f64) {
+        self.set_volume(db_to_amplitude(db));
+    }
+
+    pub fn set_pan(&mut self, pan: f64) {
+        self.set_value("VOLPAN", 1, RValue::F(clamp_pan(pan), None), &VOLPAN_DEFAULT);
+    }
+
+    /// `None` makes the track use the project's pan law.
+    pub fn set_pan_law_db(&mut self, db: Option<f64>) {
+        let law = db.map_or(-1.0, db_to_amplitude);
+        self.set_value("VOLPAN", 2, RValue::F(law, None), &VOLPAN_DEFAULT);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.set_flag("MUTESOLO", 0, muted, &MUTESOLO_DEFAULT);
+    }
+
+    pub fn set_solo(&mut self, solo: Solo) {
+        self.set_value("MUTESOLO", 1, RValue::I(solo.code(), None), &MUTESOLO_DEFAULT);
+    }
+
+    pub fn set_solo_defeat(&mut self, solo_defeat: bool) {
+        self.set_flag("MUTESOLO", 2, solo_defeat, &MUTESOLO_DEFAULT);
+    }
+
+    pub fn set_phase_inverted(&mut self, inverted: bool) {
+        self.set_flag("IPHASE", 0, inverted, &[0.0]);
+    }
+
+    pub fn set_channel_count(&mut self, channels: u32) {
+        self.set_value("NCHAN", 0, RValue::I(channels.into(), None), &[2.0]);
+    }
+
+    pub fn set_peak_color(&mut self, color: i64) {
+        self.set_value("PEAKCOL", 0, RValue::I(color, None), &[]);
+    }
+
+    /// Sets a custom `(r, g, b)` color, or restores the default color with `None`.
+    pub fn set_color(&mut self, color: Option<(u8, u8, u8)>) {
+        let color = color.map_or(DEFAULT_PEAK_COLOR, |(r, g, b)| {
+            CUSTOM_COLOR_FLAG | i64::from(b) << 16 | i64::from(g) << 8 | i64::from(r)
+        });
+        self.set_peak_color(color);
+    }
+
+    pub fn set_selected(&mut self, selected: bool) {
+        self.set_flag("SEL", 0, selected, &[0.0]);
+    }
+
+    pub fn set_armed(&mut self, armed: bool) {
+        self.set_flag("REC", 0, armed, &REC_DEFAULT);
+    }
+
+    /// See [`Track::record_input`] for the encoding.
+    pub fn set_record_input(&mut self, input: i64) {
+        self.set_value("REC", 1, RValue::I(input, None), &REC_DEFAULT);
+    }
+
+    pub fn set_record_monitor(&mut self, monitor: RecordMonitor) {
+        self.set_value("REC", 2, RValue::I(monitor.code(), None), &REC_DEFAULT);
+    }
+
+    pub fn set_record_mode(&mut self, mode: i64) {
+        self.set_value("REC", 3, RValue::I(mode, None), &REC_DEFAULT);
+    }
+
+    pub fn set_shown_in_mixer(&mut self, shown: bool) {
+        self.set_flag("SHOWINMIX", 0, shown, &SHOWINMIX_DEFAULT);
+    }
+
+    pub fn set_shown_in_tcp(&mut self, shown: bool) {
+        self.set_flag("SHOWINMIX", 3, shown, &SHOWINMIX_DEFAULT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_float_eq::*;
+
+    use crate::parser::{parse_chunk, parse_project};
+
+    use super::*;
+
+    #[test]
+    fn volume_pan_and_pan_law() {
+        let mut element = parse_chunk("<TRACK\n  VOLPAN 0.5 -0.25 0.707946 -1 1\n>").unwrap();
+        let track = Track(&element);
+        assert_float_absolute_eq!(track.volume_db(), -6.0206, 0.0001);
+        assert_eq!(track.pan(), -0.25);
+        assert_float_absolute_eq!(track.pan_law_db().unwrap(), -3.0, 0.0001);
+
+        let mut track = TrackMut(&mut element);
+        track.set_volume_db(0.0);
+        track.set_pan(3.0);
+        track.set_pan_law_db(None);
+        assert_eq!((track.track().pan(), track.track().pan_law_db()), (1.0, None));
+        assert_eq!(element.to_string(), "<TRACK\n  VOLPAN 1 1 -1 -1 1\n>");
+    }
+
+    #[test]
+    fn unnamed_codes_are_kept() {
+        let mut element = parse_chunk("<TRACK\n  MUTESOLO 0 5 0\n  REC 0 0 7 0 0 0 0\n>").unwrap();
+        assert_eq!((Track(&element).solo(), Track(&element).record_monitor()), (Solo::Other(5), RecordMonitor::Other(7)));
+
+        TrackMut(&mut element).set_solo(Solo::SoloInPlace);
+        TrackMut(&mut element).set_record_monitor(RecordMonitor::Other(9));
+        assert_eq!(element.to_string(), "<TRACK\n  MUTESOLO 0 2 0\n  REC 0 0 9 0 0 0 0\n>");
+    }
+
+    #[test]
+    fn custom_color() {
+        let mut element = parse_chunk("<TRACK\n  PEAKCOL 33521664\n>").unwrap();
+        assert_eq!(Track(&element).color(), Some((0x00, 0x80, 0xFF)));
+
+        TrackMut(&mut element).set_color(Some((0x12, 0x34, 0x56)));
+        assert_eq!(Track(&element).peak_color(), 22426642);
+        TrackMut(&mut element).set_color(None);
+        assert_eq!((Track(&element).color(), Track(&element).peak_color()), (None, DEFAULT_PEAK_COLOR));
+    }
+
+    #[test]
+    fn missing_values_are_those_of_a_new_track() {
+        let project = parse_project(include_str!("../../StreamingPlugin.rpp")).unwrap();
+        let track = &project.tracks()[0];
+        assert_eq!((track.volume_db(), track.pan(), track.pan_law_db()), (0.0, 0.0, None));
+        assert_eq!((track.solo(), track.color(), track.record_monitor()), (Solo::Off, None, RecordMonitor::On));
+
+        let mut element = parse_chunk("<TRACK\n  SHOWINMIX 1\n>").unwrap();
+        let track = Track(&element);
+        assert_eq!((track.guid(), track.volume(), track.channel_count()), (None, 1.0, 2));
+        assert!(track.is_shown_in_mixer() && track.is_shown_in_tcp() && !track.is_armed());
+
+        TrackMut(&mut element).set_shown_in_tcp(false);
+        TrackMut(&mut element).set_armed(true);
+        assert_eq!(element.to_string(), "<TRACK\n  SHOWINMIX 1 0.6667 0.5 0\n  REC 1 0 1 0 0 0 0\n>");
+    }
+
+    #[test]
+    fn new_attributes_go_before_children() {
+        let mut element = parse_chunk("<TRACK\n  NAME x\n  <FXCHAIN\n  >\n>\n").unwrap();
+        let guid = Guid::generate();
+        let mut track = TrackMut(&mut element);
+        track.set_guid(guid);
+        track.set_channel_count(6);
+        track.set_name("New name");
+        assert_eq!(track.track().guid(), Some(guid));
+        assert_eq!(
+            element.to_string(),
+            format!("<TRACK {guid}\n  NAME \"New name\"\n  TRACKID {guid}\n  NCHAN 6\n  <FXCHAIN\n  >\n>\n")
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{DecodeBase64Error, RElement, RValue};
+
+/// Second word of a VST state header, after the plugin's unique ID.
+const MAGICS: [u32; 2] = [0xFEED_5EEE, 0xFEED_5EEF];
+
+/// Why the header or state of a plugin element could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginStateError {
+    /// The element is not of the expected kind, e.g. a `JS` element passed as a VST.
+    WrongTag(String),
+    /// A header argument is missing or has the wrong type.
+    InvalidArg(usize),
+    InvalidId(String),
+    Base64(DecodeBase64Error),
+    BadMagic(u32),
+    /// The state ends in the middle of the named section.
+    Truncated(&'static str),
+    /// A configuration embedded in the plugin's chunk could not be read.
+    InvalidConfig(String),
+    /// The plugin is not the one a decoder was written for.
+    UnexpectedPlugin(String),
+}
+
+impl fmt::Display for PluginStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginStateError::WrongTag(tag) => write!(f, "unexpected plugin element {tag}"),
+            PluginStateError::InvalidArg(index) => write!(f, "missing or invalid header argument {index}"),
+            PluginStateError::InvalidId(id) => write!(f, "invalid plugin ID `{id}`"),
+            PluginStateError::Base64(e) => e.fmt(f),
+            PluginStateError::BadMagic(magic) => write!(f, "unexpected state header magic {magic:#010X}"),
+            PluginStateError::Truncated(section) => write!(f, "state ends in the {section}"),
+            PluginStateError::InvalidConfig(reason) => write!(f, "invalid plugin configuration: {reason}"),
+            PluginStateError::UnexpectedPlugin(name) => write!(f, "unexpected plugin {name}"),
+        }
+    }
+}
+
+impl std::error::Error for PluginStateError {}
+
+impl From<DecodeBase64Error> for PluginStateError {
+    fn from(e: DecodeBase64Error) -> Self {
+        PluginStateError::Base64(e)
+    }
+}
+
+/// The ID argument of a `<VST` element: the plugin's unique ID, followed by a class ID in angle
+/// brackets for VST2 plugins (`54811357<56535403445ADD73747265616D696E67>`) or in braces for VST3
+/// plugins. Projects from old REAPER versions have the unique ID only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VstId {
+    pub unique_id: u32,
+    pub class_id: Option<[u8; 16]>,
+    pub vst3: bool,
+}
+
+impl fmt::Display for VstId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.unique_id)?;
+        if let Some(class_id) = self.class_id {
+            let (open, close) = if self.vst3 { ('{', '}') } else { ('<', '>') };
+            write!(f, "{open}")?;
+            for b in class_id {
+                write!(f, "{b:02X}")?;
+            }
+            write!(f, "{close}")?;
+        }
+        Ok(())
+    }
+}
+
+impl VstId {
+    /// The ID in the fifth argument of a `VST` element, written as a plain number when it has no
+    /// class ID.
+    pub(crate) fn of(element: &RElement) -> Result<Self, PluginStateError> {
+        match element.args.get(4) {
+            Some(RValue::S(id) | RValue::QS(id)) => id.parse(),
+            Some(RValue::I(id, _)) => Ok(VstId {
+                unique_id: *id as u32,
+                class_id: None,
+                vst3: false,
+            }),
+            _ => Err(PluginStateError::InvalidArg(4)),
+        }
+    }
+}
+
+impl FromStr for VstId {
+    type Err = PluginStateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PluginStateError::InvalidId(s.to_string());
+        let digits = s.find(['<', '{']).unwrap_or(s.len());
+        let unique_id = match s[..digits].parse::<u32>() {
+            Ok(id) => id,
+            Err(_) => s[..digits].parse::<i32>().map_err(|_| invalid())? as u32,
+        };
+
+        let rest = &s[digits..];
+        let (class_id, vst3) = match rest.chars().next() {
+            None => (None, false),
+            Some(open) => {
+                let (close, vst3) = if open == '{' { ('}', true) } else { ('>', false) };
+                let hex = rest[1..].strip_suffix(close).filter(|hex| hex.len() == 32).ok_or_else(invalid)?;
+                let mut class_id = [0u8; 16];
+                for (i, b) in class_id.iter_mut().enumerate() {
+                    *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+                }
+                (Some(class_id), vst3)
+            }
+        };
+        Ok(VstId { unique_id, class_id, vst3 })
+    }
+}
+
+/// A decoded `<VST` element: its header arguments and the state REAPER keeps for the plugin.
+///
+/// The state is a header with the plugin's pin mappings, the plugin's own chunk, which is opaque to
+/// REAPER, and a trailer with the current program name. Parts of the header and trailer this
+/// crate does not interpret are kept, so that [`VstPlugin::write_to`] writes them back unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VstPlugin {
+    /// Display name, e.g. `VST: ReaEQ (Cockos)`.
+    pub name: String,
+    pub file: String,
+    pub flags: i64,
+    /// Name given to the instance by the user, empty if none.
+    pub custom_name: String,
+    pub id: VstId,
+    /// For each input pin of the plugin, a mask of the track channels connected to it.
+    pub inputs: Vec<u64>,
+    /// For each output pin of the plugin, a mask of the track channels it writes to.
+    pub outputs: Vec<u64>,
+    /// The plugin's own state, as returned by its `effGetChunk`.
+    pub chunk: Vec<u8>,
+    pub program_name: String,
+    /// First word of the state header, usually the plugin's unique ID.
+    header_id: u32,
+    magic: u32,
+    header_tail: Vec<u8>,
+    trailer_head: u8,
+    trailer_tail: Vec<u8>,
+}
+
+/// Reads little-endian words from a plugin state.
+pub(crate) struct StateReader<'b> {
+    bytes: &'b [u8],
+    section: &'static str,
+}
+
+impl<'b> StateReader<'b> {
+    pub fn new(bytes: &'b [u8]) -> Self {
+        Self { bytes, section: "header" }
+    }
+
+    /// Names the part of the state read next, for errors.
+    pub fn section(&mut self, section: &'static str) {
+        self.section = section;
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'b [u8], PluginStateError> {
+        if self.bytes.len() < len {
+            return Err(PluginStateError::Truncated(self.section));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, PluginStateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, PluginStateError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, PluginStateError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, PluginStateError> {
+        Ok(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn rest(&mut self) -> &'b [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+}
+
+impl VstPlugin {
+    pub fn from_element(element: &RElement) -> Result<Self, PluginStateError> {
+        if element.tag != "VST" {
+            return Err(PluginStateError::WrongTag(element.tag.to_string()));
+        }
+        let str_arg = |index| element.get_str_arg(index).ok_or(PluginStateError::InvalidArg(index));
+        let name = str_arg(0)?.to_string();
+        let file = str_arg(1)?.to_string();
+        let flags = element.args.get(2).and_then(RValue::get_i64).ok_or(PluginStateError::InvalidArg(2))?;
+        let custom_name = str_arg(3)?.to_string();
+        let id = VstId::of(element)?;
+
+        let bytes = element.bin_data_bytes()?;
+        let mut reader = StateReader::new(&bytes);
+        let header_id = reader.u32()?;
+        let magic = reader.u32()?;
+        if !MAGICS.contains(&magic) {
+            return Err(PluginStateError::BadMagic(magic));
+        }
+        let mut pins = || -> Result<Vec<u64>, PluginStateError> {
+            let count = reader.u32()?;
+            (0..count).map(|_| reader.u64()).collect()
+        };
+        let inputs = pins()?;
+        let outputs = pins()?;
+        let chunk_len = reader.u32()? as usize;
+        let header_tail = reader.bytes(8)?.to_vec();
+
+        reader.section("plugin chunk");
+        let chunk = reader.bytes(chunk_len)?.to_vec();
+
+        reader.section("program name");
+        let trailer_head = reader.u8()?;
+        let trailer = reader.rest();
+        let name_len = trailer.iter().position(|b| *b == 0).ok_or(PluginStateError::Truncated("program name"))?;
+        let program_name = String::from_utf8_lossy(&trailer[..name_len]).into_owned();
+        let trailer_tail = trailer[name_len + 1..].to_vec();
+
+        Ok(VstPlugin {
+            name,
+            file,
+            flags,
+            custom_name,
+            id,
+            inputs,
+            outputs,
+            chunk,
+            program_name,
+            header_id,
+            magic,
+            header_tail,
+            trailer_head,
+            trailer_tail,
+        })
+    }
+
+    /// Writes the header arguments and the state into `element`, which should be the `<VST`
+    /// element this was read from. Arguments after the ID are left as they are.
+    pub fn write_to(&self, element: &mut RElement) {
+        let args = [
+            RValue::QS(self.name.clone().into()),
+            RValue::S(self.file.clone().into()),
+            RValue::I(self.flags, None),
+            RValue::QS(self.custom_name.clone().into()),
+            RValue::S(self.id.to_string().into()),
+        ];
+        for (index, arg) in args.into_iter().enumerate() {
+            match element.args.get_mut(index) {
+                Some(current) if *current == arg => {}
+                Some(current) => *current = arg,
+                None => element.args.push(arg),
+            }
+        }
+
+        let mut header = vec![];
+        header.extend(self.header_id.to_le_bytes());
+        header.extend(self.magic.to_le_bytes());
+        for pins in [&self.inputs, &self.outputs] {
+            header.extend((pins.len() as u32).to_le_bytes());
+            for pin in pins {
+                header.extend(pin.to_le_bytes());
+            }
+        }
+        header.extend((self.chunk.len() as u32).to_le_bytes());
+        header.extend(&self.header_tail);
+
+        let mut trailer = vec![self.trailer_head];
+        trailer.extend(self.program_name.as_bytes());
+        trailer.push(0);
+        trailer.extend(&self.trailer_tail);
+
+        element.set_bin_data_blocks(&[&header, &self.chunk, &trailer]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::parse_chunk;
+
+    use super::*;
+
+    const INPUT: &str = include_str!("../../StreamingPlugin.rpp");
+
+    fn vst_element() -> RElement<'static> {
+        let project = parse_chunk(INPUT).unwrap();
+        let selector = "//VST".parse().unwrap();
+        let vst = project.select(&selector).next().unwrap().clone();
+        vst.into_owned()
+    }
+
+    #[test]
+    fn decode_streaming_plugin() {
+        let plugin = VstPlugin::from_element(&vst_element()).unwrap();
+
+        assert_eq!(plugin.name, "VST: Streaming Plugin (Distopik)");
+        assert_eq!(plugin.file, "StreamingPlugin.vst");
+        assert_eq!(plugin.custom_name, "");
+        assert_eq!(plugin.id.unique_id, 54811357);
+        assert_eq!(&plugin.id.class_id.unwrap()[7..], b"streaming");
+        assert!(!plugin.id.vst3);
+        assert_eq!(plugin.inputs, [0b01, 0b10]);
+        assert_eq!(plugin.outputs, [0b01, 0b10]);
+        assert!(plugin.chunk.starts_with(b"{\"session_id\":null"));
+        assert_eq!(plugin.program_name, "Program 1");
+    }
+
+    #[test]
+    fn write_back() {
+        let mut element = vst_element();
+        let original = element.to_string();
+        let mut plugin = VstPlugin::from_element(&element).unwrap();
+
+        plugin.write_to(&mut element);
+        assert_eq!(element.to_string(), original);
+
+        plugin.chunk = b"{}".to_vec();
+        plugin.program_name = "Live".to_string();
+        plugin.custom_name = "Stream out".to_string();
+        plugin.outputs.push(0b1100);
+        plugin.write_to(&mut element);
+
+        let reread = VstPlugin::from_element(&element).unwrap();
+        assert_eq!(reread, plugin);
+        assert_eq!(element.get_str_arg(3), Some("Stream out"));
+    }
+
+    #[test]
+    fn header_word_is_kept() {
+        let mut element = vst_element();
+        let mut bytes = element.bin_data_bytes().unwrap();
+        bytes[..4].copy_from_slice(&7u32.to_le_bytes());
+        element.set_bin_data_bytes(&bytes);
+
+        let plugin = VstPlugin::from_element(&element).unwrap();
+        assert_eq!(plugin.id.unique_id, 54811357);
+        plugin.write_to(&mut element);
+        assert_eq!(element.bin_data_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn ids() {
+        for text in ["54811357<56535403445ADD73747265616D696E67>", "1997878177{5653545265716572656173746F726561}", "1919247729"] {
+            assert_eq!(text.parse::<VstId>().unwrap().to_string(), text);
+        }
+        assert!("{5653545265716572656173746F726561}".parse::<VstId>().is_err());
+        assert!("1<5653>".parse::<VstId>().is_err());
+        assert_eq!("-1".parse::<VstId>().unwrap().unique_id, u32::MAX);
+    }
+
+    #[test]
+    fn errors() {
+        let element = parse_chunk("<JS loser/3BandEQ \"\"\n>").unwrap();
+        assert_eq!(VstPlugin::from_element(&element), Err(PluginStateError::WrongTag("JS".to_string())));
+
+        let mut element = vst_element();
+        element.set_bin_data_bytes(&[0; 8]);
+        assert_eq!(VstPlugin::from_element(&element), Err(PluginStateError::BadMagic(0)));
+
+        let mut bytes = vst_element().bin_data_bytes().unwrap();
+        bytes.truncate(70);
+        element.set_bin_data_bytes(&bytes);
+        assert_eq!(VstPlugin::from_element(&element), Err(PluginStateError::Truncated("plugin chunk")));
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::{Error, RElement, RFragment, RValue};
+
+/// A path through an element tree, such as `TRACK/FXCHAIN/VST[0]`, `//ITEM[NAME~="Bass"]` or
+/// `TRACK[@TRACKID={E8B281C6-3542-394A-AAD4-A5875512F906}]/@NAME`.
+///
+/// * `/` separates steps that match direct children, `//` steps that match descendants at any
+///   depth. A leading `//` searches the whole tree; otherwise the first step matches children of
+///   the element the selector is applied to.
+/// * A step is a tag or `*`, followed by any number of predicates:
+///   * `[n]` keeps the n-th match (0-based) among siblings,
+///   * `[NAME]` or `[@NAME]` keeps elements that have a `NAME` attribute,
+///   * `[NAME=value]`, `[NAME!=value]` and `[NAME~=value]` compare the attribute's first value
+///     (equal, not equal, contains). Values may be quoted; numbers compare numerically.
+/// * A final `/@NAME` selects attributes instead of elements, for use with
+///   [`RElement::select_attrs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+  pub(crate) steps: Vec<Step>,
+  pub(crate) attribute: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Step {
+  pub descendant: bool,
+  /// `None` for `*`.
+  pub tag: Option<String>,
+  pub predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Predicate {
+  Index(usize),
+  HasAttr(String),
+  Attr(String, Op, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+  Eq,
+  Ne,
+  Contains,
+}
+
+impl Selector {
+  pub fn parse(selector: &str) -> Result<Self, Error> {
+    crate::parser::parse_selector(selector)
+  }
+}
+
+impl FromStr for Selector {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::parse(s)
+  }
+}
+
+fn value_text(value: &RValue) -> String {
+  match value {
+    RValue::QS(s) | RValue::S(s) => s.to_string(),
+    value => value.to_string(),
+  }
+}
+
+fn value_equals(value: &RValue, literal: &str) -> bool {
+  match (value.get_num(), literal.parse::<f64>()) {
+    (Some(a), Ok(b)) => a == b,
+    _ => value_text(value) == literal,
+  }
+}
+
+impl Predicate {
+  fn matches(&self, element: &RElement) -> bool {
+    match self {
+      Predicate::Index(_) => true,
+      Predicate::HasAttr(name) => element.attr_values(name).is_some(),
+      Predicate::Attr(name, op, literal) => {
+        let value = element.attr_values(name).and_then(<[RValue]>::first);
+        match (value, op) {
+          (Some(value), Op::Eq) => value_equals(value, literal),
+          (Some(value), Op::Ne) => !value_equals(value, literal),
+          (Some(value), Op::Contains) => value_text(value).contains(literal.as_str()),
+          (None, Op::Ne) => true,
+          (None, _) => false,
+        }
+      }
+    }
+  }
+}
+
+impl Step {
+  /// Content indices of the children of `parent` matched by this step, in document order.
+  fn matching_children(&self, parent: &RElement) -> Vec<usize> {
+    let mut matched: Vec<usize> = parent
+      .content
+      .iter()
+      .enumerate()
+      .filter_map(|(i, frag)| match frag {
+        RFragment::Child(c) if self.tag.as_deref().is_none_or(|tag| c.tag == tag) => Some(i),
+        _ => None,
+      })
+      .collect();
+
+    for predicate in &self.predicates {
+      matched = match predicate {
+        Predicate::Index(n) => matched.get(*n).copied().into_iter().collect(),
+        predicate => matched
+          .into_iter()
+          .filter(|i| matches!(&parent.content[*i], RFragment::Child(c) if predicate.matches(c)))
+          .collect(),
+      };
+    }
+    matched
+  }
+}
+
+fn child_at<'s, 'a>(element: &'s RElement<'a>, index: usize) -> &'s RElement<'a> {
+  match &element.content[index] {
+    RFragment::Child(c) => c,
+    _ => unreachable!("selector paths only point at child elements"),
+  }
+}
+
+fn child_at_mut<'s, 'a>(element: &'s mut RElement<'a>, index: usize) -> &'s mut RElement<'a> {
+  match &mut element.content[index] {
+    RFragment::Child(c) => c,
+    _ => unreachable!("selector paths only point at child elements"),
+  }
+}
+
+impl Selector {
+  /// Content-index paths from `root` to every matching element, in document order.
+  fn paths(&self, root: &RElement) -> Vec<Vec<usize>> {
+    fn walk(element: &RElement, steps: &[Step], path: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+      let Some((step, rest)) = steps.split_first() else {
+        out.push(path.clone());
+        return;
+      };
+
+      let matched: HashSet<usize> = step.matching_children(element).into_iter().collect();
+      for (i, frag) in element.content.iter().enumerate() {
+        let RFragment::Child(child) = frag else { continue };
+        path.push(i);
+        if matched.contains(&i) {
+          walk(child, rest, path, out);
+        }
+        if step.descendant {
+          walk(child, steps, path, out);
+        }
+        path.pop();
+      }
+    }
+
+    let mut out = vec![];
+    walk(root, &self.steps, &mut vec![], &mut out);
+    // Index paths sort lexicographically in document order.
+    out.sort();
+    out.dedup();
+    if let Some(name) = &self.attribute {
+      out.retain(|path| Self::resolve(root, path).attr_values(name).is_some());
+    }
+    out
+  }
+
+  fn resolve<'s, 'a>(root: &'s RElement<'a>, path: &[usize]) -> &'s RElement<'a> {
+    path.iter().fold(root, |element, i| child_at(element, *i))
+  }
+
+  fn resolve_mut<'s, 'a>(root: &'s mut RElement<'a>, path: &[usize]) -> &'s mut RElement<'a> {
+    path.iter().fold(root, |element, i| child_at_mut(element, *i))
+  }
+}
+
+impl<'a> RElement<'a> {
+  /// Elements matched by `selector`, in document order. A selector ending in `/@NAME` matches the
+  /// elements that have that attribute.
+  pub fn select<'s>(&'s self, selector: &Selector) -> impl Iterator<Item=&'s RElement<'a>> + 's {
+    selector
+      .paths(self)
+      .into_iter()
+      .map(move |path| Selector::resolve(self, &path))
+  }
+
+  /// Values of the attributes matched by a selector ending in `/@NAME`, every occurrence of the
+  /// attribute in document order. Yields nothing for selectors without an attribute step.
+  pub fn select_attrs<'s>(&'s self, selector: &Selector) -> impl Iterator<Item=&'s [RValue<'a>]> + 's {
+    let name = selector.attribute.clone().unwrap_or_default();
+    let elements = match selector.attribute {
+      Some(_) => self.select(selector).collect(),
+      None => vec![],
+    };
+    elements.into_iter().flat_map(move |element| {
+      let name = name.clone();
+      element.content.iter().filter_map(move |frag| match frag {
+        RFragment::Attribute(attrib_name, values) if *attrib_name == name => Some(values.as_slice()),
+        _ => None,
+      })
+    })
+  }
+
+  /// Calls `f` on every element matched by `selector` and returns how many there were.
+  ///
+  /// Matches are visited in reverse document order, so `f` is free to restructure the content of
+  /// the element it is given without invalidating the matches still to come.
+  pub fn select_mut<F>(&mut self, selector: &Selector, mut f: F) -> usize
+    where
+      F: FnMut(&mut RElement<'a>),
+  {
+    let paths = selector.paths(self);
+    for path in paths.iter().rev() {
+      f(Selector::resolve_mut(self, path));
+    }
+    paths.len()
+  }
+
+  /// Calls `f` on the values of every attribute matched by a selector ending in `/@NAME`, in
+  /// reverse document order, and returns how many there were.
+  pub fn select_attrs_mut<F>(&mut self, selector: &Selector, mut f: F) -> usize
+    where
+      F: FnMut(&mut Vec<RValue<'a>>),
+  {
+    let Some(name) = &selector.attribute else { return 0 };
+    let mut count = 0;
+    self.select_mut(selector, |element| {
+      for frag in element.content.iter_mut().rev() {
+        if let RFragment::Attribute(attrib_name, values) = frag {
+          if attrib_name == name {
+            f(values);
+            count += 1;
+          }
+        }
+      }
+    });
+    count
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::{parse_chunk, RValues};
+
+  use super::*;
+
+  const PROJECT: &str = r#"<REAPER_PROJECT 0.1 "6.43/macOS-arm64" 1640941958
+  <TRACK {E8B281C6-3542-394A-AAD4-A5875512F906}
+    NAME "Bass DI"
+    <FXCHAIN
+      <VST "VST: ReaEQ (Cockos)" reaeq.vst 0 "" 1919247729
+      >
+      <VST "VST: ReaComp (Cockos)" reacomp.vst 0 "" 1919247213
+      >
+    >
+    <ITEM
+      NAME "Bass take"
+      POSITION 0
+    >
+  >
+  <TRACK {F5E7B582-5137-2A43-8FF8-9FCBC708804D}
+    NAME Drums
+    <ITEM
+      NAME Kick
+      POSITION 2
+    >
+    <ITEM
+      NAME "Bass drum"
+      POSITION 4
+    >
+  >
+>
+"#;
+
+  fn select<'s>(root: &'s RElement, selector: &str) -> Vec<&'s RElement<'s>> {
+    root.select(&selector.parse().unwrap()).collect()
+  }
+
+  #[test]
+  fn child_steps_and_index() {
+    let project = parse_chunk(PROJECT).unwrap();
+    let vst = select(&project, "TRACK/FXCHAIN/VST[0]");
+    assert_eq!(vst.len(), 1);
+    assert_eq!(vst[0].get_str_arg(1), Some("reaeq.vst"));
+
+    assert_eq!(select(&project, "TRACK/FXCHAIN/VST").len(), 2);
+    assert_eq!(select(&project, "TRACK[1]/ITEM").len(), 2);
+    assert_eq!(select(&project, "ITEM").len(), 0);
+    assert_eq!(select(&project, "*/*").len(), 4);
+  }
+
+  #[test]
+  fn descendants_with_predicates() {
+    let project = parse_chunk(PROJECT).unwrap();
+    let names: Vec<_> = select(&project, r#"//ITEM[NAME~="Bass"]"#)
+      .iter()
+      .map(|item| item.get_str_attr("NAME", 0).unwrap())
+      .collect();
+    assert_eq!(names, vec!["Bass take", "Bass drum"]);
+
+    assert_eq!(select(&project, "//ITEM[POSITION=4.0]").len(), 1);
+    assert_eq!(select(&project, "//ITEM[POSITION!=0]").len(), 2);
+    assert_eq!(select(&project, "//ITEM[0]").len(), 2);
+    assert_eq!(select(&project, "//VST").len(), 2);
+    assert_eq!(select(&project, "//TRACK//VST").len(), 2);
+
+    let nested = parse_chunk("<R\n  <A\n    <C first\n    >\n    <A\n      <C second\n      >\n    >\n    <C third\n    >\n  >\n>\n").unwrap();
+    let found: Vec<_> = select(&nested, "//A/C").iter().map(|c| c.get_str_arg(0).unwrap().to_string()).collect();
+    assert_eq!(found, ["first", "second", "third"]);
+  }
+
+  #[test]
+  fn guid_args_as_attributes() {
+    let input = PROJECT.replace("    NAME Drums\n", "    NAME Drums\n    TRACKID {F5E7B582-5137-2A43-8FF8-9FCBC708804D}\n");
+    let project = parse_chunk(&input).unwrap();
+    let tracks = select(&project, "TRACK[@TRACKID={F5E7B582-5137-2A43-8FF8-9FCBC708804D}]");
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].get_str_attr("NAME", 0), Some("Drums"));
+    assert_eq!(select(&project, "TRACK[TRACKID]").len(), 1);
+  }
+
+  #[test]
+  fn attributes() {
+    let project = parse_chunk(PROJECT).unwrap();
+    let selector = Selector::parse("//ITEM/@POSITION").unwrap();
+    let positions: Vec<f64> = project.select_attrs(&selector).map(|v| v[0].get_num().unwrap()).collect();
+    assert_eq!(positions, vec![0.0, 2.0, 4.0]);
+    assert_eq!(project.select(&selector).count(), 3);
+  }
+
+  #[test]
+  fn bulk_edits() {
+    let mut project = parse_chunk(PROJECT).unwrap();
+    let count = project.select_attrs_mut(&"//ITEM/@POSITION".parse().unwrap(), |values| {
+      let position = values[0].get_num().unwrap();
+      *values = RValues::float(position + 1.0);
+    });
+    assert_eq!(count, 3);
+
+    let count = project.select_mut(&"TRACK/FXCHAIN".parse().unwrap(), |fx_chain| {
+      fx_chain.remove_children_with_tag("VST");
+    });
+    assert_eq!(count, 1);
+    assert_eq!(select(&project, "//VST").len(), 0);
+    assert_eq!(select(&project, "//ITEM[POSITION=5]").len(), 1);
+  }
+
+  #[test]
+  fn parse_errors() {
+    let error = Selector::parse("TRACK/[0]").unwrap_err();
+    assert_eq!(error.column, 7);
+    assert!(Selector::parse("TRACK[NAME=").is_err());
+    assert!(Selector::parse("").is_err());
+  }
+}
@@ -0,0 +1,161 @@
+use std::{fmt, io};
+
+use crate::RElement;
+
+/// Line endings used by [`ChunkWriter`]. REAPER writes `\r\n` on Windows and `\n` elsewhere.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+  #[default]
+  Lf,
+  CrLf,
+}
+
+impl LineEnding {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      LineEnding::Lf => "\n",
+      LineEnding::CrLf => "\r\n",
+    }
+  }
+}
+
+/// Writes elements in RPP syntax straight to an [`io::Write`] or [`fmt::Write`], with normalized
+/// formatting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkWriter {
+  indent_width: usize,
+  line_ending: LineEnding,
+  trailing_newline: bool,
+  keep_layout: bool,
+}
+
+impl Default for ChunkWriter {
+  fn default() -> Self {
+    Self {
+      indent_width: 2,
+      line_ending: LineEnding::Lf,
+      trailing_newline: true,
+      keep_layout: false,
+    }
+  }
+}
+
+impl ChunkWriter {
+  /// Two-space indentation, `\n` line endings and a line ending after the closing `>`, the way
+  /// REAPER writes project files on macOS and Linux.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Spaces added per nesting level.
+  pub fn indent_width(mut self, width: usize) -> Self {
+    self.indent_width = width;
+    self
+  }
+
+  pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+    self.line_ending = line_ending;
+    self
+  }
+
+  /// Whether the closing `>` of the outermost element is followed by a line ending. Chunks passed
+  /// to `SetTrackStateChunk` and friends usually go without one.
+  pub fn trailing_newline(mut self, trailing_newline: bool) -> Self {
+    self.trailing_newline = trailing_newline;
+    self
+  }
+
+  /// Keeps the recorded [`RLayout`](crate::RLayout) of parsed elements, so that only elements
+  /// created in code follow the options above.
+  pub fn keep_layout(mut self, keep_layout: bool) -> Self {
+    self.keep_layout = keep_layout;
+    self
+  }
+
+  pub fn write<W: fmt::Write + ?Sized>(&self, element: &RElement, out: &mut W) -> fmt::Result {
+    element.write_to(
+      out,
+      "",
+      &" ".repeat(self.indent_width),
+      self.line_ending.as_str(),
+      self.keep_layout,
+      self.trailing_newline.then_some(""),
+    )
+  }
+
+  pub fn write_io<W: io::Write + ?Sized>(&self, element: &RElement, out: &mut W) -> io::Result<()> {
+    let mut adapter = IoAdapter { out, error: None };
+    match self.write(element, &mut adapter) {
+      Ok(()) => Ok(()),
+      Err(fmt::Error) => Err(adapter.error.unwrap_or_else(|| io::Error::other("formatter error"))),
+    }
+  }
+
+  pub fn to_string(&self, element: &RElement) -> String {
+    let mut rv = String::new();
+    self.write(element, &mut rv).expect("writing to a String cannot fail");
+    rv
+  }
+}
+
+/// Forwards to an [`io::Write`], keeping the first error so it can be returned as is.
+struct IoAdapter<'w, W: io::Write + ?Sized> {
+  out: &'w mut W,
+  error: Option<io::Error>,
+}
+
+impl<'w, W: io::Write + ?Sized> fmt::Write for IoAdapter<'w, W> {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    self.out.write_all(s.as_bytes()).map_err(|e| {
+      self.error = Some(e);
+      fmt::Error
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::parser::parse_chunk;
+  use crate::RValues;
+
+  use super::*;
+
+  const INPUT: &str = "<TRACK\n NAME bass\n <ITEM\n  LENGTH 1.5\n >\n>\n";
+
+  #[test]
+  fn options() {
+    let track = parse_chunk(INPUT).unwrap();
+
+    let written = ChunkWriter::new().to_string(&track);
+    assert_eq!(written, "<TRACK\n  NAME bass\n  <ITEM\n    LENGTH 1.5\n  >\n>\n");
+
+    let written = ChunkWriter::new()
+      .indent_width(0)
+      .line_ending(LineEnding::CrLf)
+      .trailing_newline(false)
+      .to_string(&track);
+    assert_eq!(written, "<TRACK\r\nNAME bass\r\n<ITEM\r\nLENGTH 1.5\r\n>\r\n>");
+  }
+
+  #[test]
+  fn keep_layout() {
+    let mut track = parse_chunk(INPUT).unwrap();
+    track.append_attribute("NCHAN", RValues::ints([2]));
+
+    let written = ChunkWriter::new().keep_layout(true).to_string(&track);
+    assert_eq!(written, "<TRACK\n NAME bass\n <ITEM\n  LENGTH 1.5\n >\n NCHAN 2\n>\n");
+    assert_eq!(written, track.to_string());
+  }
+
+  #[test]
+  fn io_write() {
+    let track = parse_chunk(INPUT).unwrap();
+    let mut out = vec![];
+    ChunkWriter::new().write_io(&track, &mut out).unwrap();
+    assert_eq!(out, ChunkWriter::new().to_string(&track).into_bytes());
+
+    let mut full = [0u8; 8];
+    let error = ChunkWriter::new().write_io(&track, &mut &mut full[..]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+  }
+}